@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::hash::Hash;
 
@@ -11,9 +11,10 @@ pub trait GridNode: PartialEq + Eq + Clone + Hash + PartialOrd + Ord {
 /// A pathfinding map structure.
 ///
 /// A Dijkstra map lets you run pathfinding from any graph node it covers
-/// towards or away from the target nodes of the map. Currently the structure
-/// only supports underlying graphs with a fixed grid graph where the
-/// neighbors of each node must be the adjacent grid cells of that node.
+/// towards or away from the target nodes of the map. The map is usually built
+/// over a fixed grid graph where the neighbors of each node are the adjacent
+/// grid cells of that node, but a custom neighbor function can be provided
+/// for graphs with irregular connections.
 pub struct Dijkstra<N> {
     pub weights: HashMap<N, u32>,
 }
@@ -22,6 +23,34 @@ impl<N: GridNode> Dijkstra<N> {
     /// Create a new Dijkstra map up to limit distance from goals, omitting
     /// nodes for which the is_valid predicate returns false.
     pub fn new<F: Fn(&N) -> bool>(goals: Vec<N>, is_valid: F, limit: u32) -> Dijkstra<N> {
+        Dijkstra::with_neighbors(goals, |n| n.neighbors(), is_valid, limit)
+    }
+
+    /// Return the neighbors of a cell (if any), sorted from downhill to
+    /// uphill.
+    pub fn sorted_neighbors(&self, node: &N) -> Vec<N> {
+        let mut ret = Vec::new();
+        for n in &node.neighbors() {
+            if let Some(w) = self.weights.get(n) {
+                ret.push((w, n.clone()));
+            }
+        }
+        ret.sort_by(|&(w1, _), &(w2, _)| w1.cmp(w2));
+        ret.into_iter().map(|(_, n)| n).collect()
+    }
+}
+
+impl<N: Eq + Hash + Clone> Dijkstra<N> {
+    /// Create a new Dijkstra map using a custom neighbor function.
+    ///
+    /// The neighbor function is used to expand the map outwards from the
+    /// goals, so for directed graphs it must return the nodes from which the
+    /// given node can be reached.
+    pub fn with_neighbors<F, G>(goals: Vec<N>, neighbors: G, is_valid: F, limit: u32) -> Dijkstra<N>
+    where
+        F: Fn(&N) -> bool,
+        G: Fn(&N) -> Vec<N>,
+    {
         assert!(!goals.is_empty());
 
         let mut weights = HashMap::new();
@@ -38,7 +67,7 @@ impl<N: GridNode> Dijkstra<N> {
 
             let mut new_edge = HashSet::new();
             for n in &edge {
-                for m in n.neighbors() {
+                for m in neighbors(n) {
                     if is_valid(&m) && !weights.contains_key(&m) {
                         new_edge.insert(m);
                     }
@@ -55,17 +84,50 @@ impl<N: GridNode> Dijkstra<N> {
         Dijkstra { weights }
     }
 
-    /// Return the neighbors of a cell (if any), sorted from downhill to
-    /// uphill.
-    pub fn sorted_neighbors(&self, node: &N) -> Vec<N> {
-        let mut ret = Vec::new();
-        for n in &node.neighbors() {
-            if let Some(w) = self.weights.get(n) {
-                ret.push((w, n.clone()));
+    /// Create a map for fleeing from the goals of this map.
+    ///
+    /// Moving downhill on the inverted map leads away from the goals. The distances are scaled up
+    /// before being inverted, so the flight path will prefer going around the goals to reach open
+    /// areas over retreating into the nearest dead end. The neighbor function works the same as
+    /// in `with_neighbors`, the inverted map only covers nodes of the original map.
+    pub fn inverted<G>(&self, neighbors: G) -> Dijkstra<N>
+    where
+        N: Ord,
+        G: Fn(&N) -> Vec<N>,
+    {
+        // Weights are in tenths of a step, moving one step costs 10, being one step further from
+        // the goals is worth 12.
+        const STEP: u32 = 10;
+        const SCALE: u32 = 12;
+
+        let max = self.weights.values().cloned().max().unwrap_or(0);
+        let mut weights: HashMap<N, u32> = self
+            .weights
+            .iter()
+            .map(|(n, &w)| (n.clone(), (max - w) * SCALE))
+            .collect();
+
+        let mut open: BinaryHeap<Reverse<(u32, N)>> = weights
+            .iter()
+            .map(|(n, &w)| Reverse((w, n.clone())))
+            .collect();
+
+        while let Some(Reverse((w, n))) = open.pop() {
+            if weights[&n] < w {
+                // Stale entry.
+                continue;
+            }
+            for m in neighbors(&n) {
+                if let Some(old) = weights.get_mut(&m) {
+                    if *old > w + STEP {
+                        *old = w + STEP;
+                        open.push(Reverse((w + STEP, m)));
+                    }
+                }
             }
         }
-        ret.sort_by(|&(w1, _), &(w2, _)| w1.cmp(w2));
-        ret.into_iter().map(|(_, n)| n).collect()
+
+        Dijkstra { weights }
     }
 }
 
@@ -180,4 +242,44 @@ mod test {
             astar_path(8, &12, |x| neighbors(8, x))
         );
     }
+
+    #[test]
+    fn test_dijkstra_with_neighbors() {
+        // Integer line with a shortcut between 0 and 10.
+        let neighbors = |&x: &i32| match x {
+            0 => vec![-1, 1, 10],
+            10 => vec![9, 11, 0],
+            x => vec![x - 1, x + 1],
+        };
+        let map = Dijkstra::with_neighbors(vec![0], neighbors, |x| (0..=12).contains(x), 100);
+        assert_eq!(Some(&0), map.weights.get(&0));
+        assert_eq!(Some(&1), map.weights.get(&10));
+        assert_eq!(Some(&2), map.weights.get(&9));
+        assert_eq!(Some(&3), map.weights.get(&3));
+        assert_eq!(None, map.weights.get(&-1));
+        assert_eq!(None, map.weights.get(&13));
+    }
+
+    #[test]
+    fn test_dijkstra_inverted() {
+        let neighbors = |&x: &i32| vec![x - 1, x + 1];
+        let map = Dijkstra::with_neighbors(vec![4], neighbors, |x| (0..=12).contains(x), 100);
+        let flee = map.inverted(neighbors);
+        assert_eq!(None, flee.weights.get(&13));
+
+        // Walking downhill from next to the goal leads to the far end.
+        let mut pos = 5;
+        loop {
+            let next = neighbors(&pos)
+                .into_iter()
+                .filter_map(|n| flee.weights.get(&n).map(|&w| (w, n)))
+                .min()
+                .unwrap();
+            if next.0 >= flee.weights[&pos] {
+                break;
+            }
+            pos = next.1;
+        }
+        assert_eq!(12, pos);
+    }
 }
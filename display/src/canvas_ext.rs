@@ -13,6 +13,9 @@ pub trait CanvasExt {
     fn draw_item_icon(&mut self, pos: Point2D<i32>, icon: Icon, count: u32) {
         self.draw_entity(pos + vec2(0, -2), icon, count); // TODO
     }
+
+    /// Show the remaining charges of an item drawn with `draw_item_icon`.
+    fn draw_item_charges(&mut self, pos: Point2D<i32>, charges: u32);
 }
 
 impl CanvasExt for Canvas<'_> {
@@ -38,4 +41,19 @@ impl CanvasExt for Canvas<'_> {
             );
         }
     }
+
+    fn draw_item_charges(&mut self, pos: Point2D<i32>, charges: u32) {
+        self.draw_outline_text(
+            &*cache::tiny_font(),
+            pos + vec2(9, 3),
+            Align::Right,
+            if charges > 0 {
+                color::CYAN
+            } else {
+                color::GRAY5
+            },
+            color::GRAY2,
+            &format!("{}", charges),
+        );
+    }
 }
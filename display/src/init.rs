@@ -161,17 +161,29 @@ pub fn entity_brushes() -> VecMap<Arc<Brush>> {
     ret.insert(Dog as usize, Builder::new("assets/mobs.png").color(OLIVE).mob(5*32, 3*32).finish());
     ret.insert(Cat as usize, Builder::new("assets/mobs.png").color(LIGHTSLATEGRAY).mob(6*32, 3*32).finish());
     ret.insert(Rat as usize, Builder::new("assets/mobs.png").color(ROSYBROWN).mob(7*32, 3*32).finish());
+    ret.insert(Shopkeeper as usize, Builder::new("assets/mobs.png").color(PLUM).mob(0, 0).finish());
 
     ret.insert(PlaceholderMob as usize, Builder::new("assets/mobs.png").color(LIGHTGRAY).mob(0*32, 3*32).finish());
     ret.insert(InvisibleMob as usize, Builder::new("assets/mobs.png").color(LIGHTGRAY).mob(1*32, 3*32).finish());
 
     ret.insert(Sword as usize, Builder::new("assets/items.png").color(LIGHTGRAY).item(0*16, 0*16).finish());
+    ret.insert(Bow as usize, Builder::new("assets/items.png").color(SADDLEBROWN).item(3*16, 0*16).finish());
     ret.insert(Helmet as usize, Builder::new("assets/items.png").color(LIGHTGRAY).item(1*16, 0*16).finish());
     ret.insert(Armor as usize, Builder::new("assets/items.png").color(LIGHTGRAY).item(6*16, 0*16).finish());
 
     ret.insert(Scroll1 as usize, Builder::new("assets/items.png").color(LIGHTYELLOW).item(0*16, 1*16).finish());
     ret.insert(Wand1 as usize, Builder::new("assets/items.png").color(RED).item(3*16, 0*16).finish());
     ret.insert(Wand2 as usize, Builder::new("assets/items.png").color(CYAN).item(3*16, 0*16).finish());
+    ret.insert(Ring1 as usize, Builder::new("assets/items.png").color(ORANGE).item(7*16, 0*16).finish());
+    ret.insert(Ration as usize, Builder::new("assets/items.png").color(SANDYBROWN).item(4*16, 0*16).finish());
+    ret.insert(Scroll2 as usize, Builder::new("assets/items.png").color(LIGHTGREEN).item(0*16, 1*16).finish());
+    ret.insert(Book as usize, Builder::new("assets/items.png").color(MEDIUMPURPLE).item(0*16, 1*16).finish());
+    ret.insert(Shield as usize, Builder::new("assets/items.png").color(PERU).item(6*16, 0*16).finish());
+    ret.insert(Orb as usize, Builder::new("assets/items.png").color(CRIMSON).item(7*16, 0*16).finish());
+    ret.insert(Gold as usize, Builder::new("assets/items.png").color(GOLD).item(5*16, 0*16).finish());
+    ret.insert(Sack as usize, Builder::new("assets/items.png").color(TAN).item(2*16, 0*16).finish());
+    ret.insert(Trap as usize, Builder::new("assets/props.png").color(LIGHTGRAY).tile(5*32, 1*32).finish());
+    ret.insert(Chest as usize, Builder::new("assets/props.png").color(SIENNA).tile(5*32, 0).finish());
     ret
 }
 
@@ -226,5 +238,11 @@ pub fn misc_brushes() -> VecMap<Arc<Brush>> {
         .tile(6*32, 1*32).merge()
         .finish());
 
+    // Small dot floating at about the height of a mob's midsection.
+    ret.insert(Projectile as usize, Builder::new("assets/gui.png")
+        .color(WHITE)
+        .splat(Some(Geom::new(2, 10, 0, 8, 4, 4)))
+        .finish());
+
     ret
 }
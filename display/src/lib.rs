@@ -25,4 +25,5 @@ pub enum Icon {
     Smoke,
     Explosion,
     Firespell,
+    Projectile,
 }
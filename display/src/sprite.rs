@@ -10,6 +10,8 @@ use vitral::{color, Canvas, Rgba};
 pub enum Coloring {
     /// Use map memory coloring for this sprite.
     MapMemory,
+    /// Use the coloring for things sensed without being seen.
+    Detected,
     /// Use the darkness level in [0.0, 1.0] for this sprite.
     Shaded { ambient: f32, diffuse: f32 },
     /// Entirely single color.
@@ -40,6 +42,7 @@ impl Coloring {
 
         match self {
             Coloring::MapMemory => (Rgba::from(0x2222_22ffu32), Rgba::from(0x0408_08ff)),
+            Coloring::Detected => (Rgba::from(0xaa66_ddffu32), Rgba::from(0x1104_22ff)),
             Coloring::Shaded { ambient, diffuse } => {
                 let (fore, back) = (
                     lerp(color::BLACK, fore, diffuse),
@@ -16,7 +16,8 @@ use std::iter::FromIterator;
 use std::sync::Arc;
 use vitral::{color, Canvas};
 use world::{
-    AnimState, FovStatus, LerpLocation, Location, PhysicsSpace, PhysicsVector, Sector, World,
+    AnimState, FovStatus, LerpLocation, Location, PhysicsSpace, PhysicsVector, Sector, Status,
+    World,
 };
 
 /// Useful general constant for cell dimension ops.
@@ -99,6 +100,8 @@ impl WorldView {
                 }
             }
 
+            let screen_pos = chart_pos.project() + center;
+
             let in_map_memory;
 
             // If the chart position is in live FOV, we want to show the deepest stack coordinate.
@@ -121,13 +124,12 @@ impl WorldView {
                 }
 
                 if !gate_point && get_fov(world, loc) != Some(FovStatus::Remembered) {
-                    // Bail out if there's no memory
+                    // Bail out if there's no memory, detected things still show up though.
+                    draw_detected(&mut sprites, world, loc, screen_pos);
                     continue;
                 }
             }
 
-            let screen_pos = chart_pos.project() + center;
-
             let ambient = world.light_level(loc);
 
             let mut terrain_sprite_buffer = Vec::new();
@@ -175,7 +177,10 @@ impl WorldView {
             let mut fx = Vec::new();
 
             for e in world.entities_at(loc) {
-                if world.is_mob(e) {
+                if world.is_hidden(e) {
+                    // Traps nobody has found yet.
+                    continue;
+                } else if world.is_mob(e) {
                     mobs.push(e);
                 } else if world.is_fx(e) {
                     fx.push(e);
@@ -188,7 +193,10 @@ impl WorldView {
             // FIXME: This should not use live entity data for the remembered objects, since it
             // will then show the object moving around without the player observing it.
             for &i in &items {
-                if let Some(desc) = world.ecs().desc.get(i) {
+                if in_map_memory && world.is_detected(i) {
+                    continue;
+                }
+                if let Some(icon) = world.entity_icon(i) {
                     let screen_pos = screen_pos + lerp_offset(world, i);
                     let color = if in_map_memory {
                         Coloring::MapMemory
@@ -199,12 +207,15 @@ impl WorldView {
                         }
                     };
                     entity_sprite_buffer.push(
-                        Sprite::new(Layer::Object, screen_pos, cache::entity(desc.icon))
-                            .color(color),
+                        Sprite::new(Layer::Object, screen_pos, cache::entity(icon)).color(color),
                     );
                 }
             }
 
+            if in_map_memory {
+                draw_detected(&mut entity_sprite_buffer, world, loc, screen_pos);
+            }
+
             // Draw mobs in directly seen cells
             if !in_map_memory {
                 const BLINK_FRAMES: u64 = 5;
@@ -213,6 +224,16 @@ impl WorldView {
                     let screen_pos = screen_pos + lerp_offset(world, i);
 
                     if let Some(desc) = world.ecs().desc.get(i) {
+                        // Invisible mobs aren't drawn, except for the player who gets a ghostly
+                        // outline.
+                        let icon = if !world.has_status(i, Status::Invisible) {
+                            desc.icon
+                        } else if world.is_player(i) {
+                            world::Icon::InvisibleMob
+                        } else {
+                            continue;
+                        };
+
                         let frame_idx = if world.is_bobbing(i) {
                             ((world.get_anim_tick() / 10) % 2) as usize
                         } else {
@@ -248,7 +269,7 @@ impl WorldView {
                         };
 
                         entity_sprite_buffer.push(
-                            Sprite::new(Layer::Object, screen_pos, cache::entity(desc.icon))
+                            Sprite::new(Layer::Object, screen_pos, cache::entity(icon))
                                 .idx(frame_idx)
                                 .color(coloring),
                         );
@@ -340,6 +361,24 @@ impl WorldView {
                             }
                         }
 
+                        if anim.state == AnimState::Projectile {
+                            let t = world.get_anim_tick() - anim.anim_start;
+
+                            if t <= anim.tween_duration as u64 {
+                                entity_sprite_buffer.push(
+                                    Sprite::new(
+                                        Layer::Effect,
+                                        screen_pos,
+                                        cache::misc(Icon::Projectile),
+                                    )
+                                    .color(Coloring::Shaded {
+                                        ambient: 1.0,
+                                        diffuse: 1.0,
+                                    }),
+                                );
+                            }
+                        }
+
                         if anim.state == AnimState::Firespell {
                             const FRAMES: usize = 2;
 
@@ -445,6 +484,27 @@ impl WorldView {
 
             loc.offset.project()
         }
+
+        /// Draw the entities the player has detected in a cell that isn't in view.
+        fn draw_detected(
+            sprites: &mut Vec<Sprite>,
+            world: &World,
+            loc: Location,
+            screen_pos: ScreenVector,
+        ) {
+            for e in world.entities_at(loc) {
+                if !world.is_detected(e) {
+                    continue;
+                }
+                if let Some(icon) = world.entity_icon(e) {
+                    let screen_pos = screen_pos + lerp_offset(world, e);
+                    sprites.push(
+                        Sprite::new(Layer::Object, screen_pos, cache::entity(icon))
+                            .color(Coloring::Detected),
+                    );
+                }
+            }
+        }
     }
 }
 
@@ -9,7 +9,7 @@ use std::io::Cursor;
 use vitral::{
     color, Align, ButtonAction, Canvas, InputEvent, Keycode, RectUtil, Rgba, Scene, SceneSwitch,
 };
-use world::{Ability, ActionOutcome, Command, LerpLocation, Slot, World, WorldSeed};
+use world::{Ability, Command, Hunger, LerpLocation, Slot, World, WorldSeed};
 
 pub struct HotbarAction {
     ability: Ability,
@@ -23,6 +23,8 @@ pub(crate) struct GameRuntime {
     cursor_item: Option<Entity>,
     hotbar: [Option<HotbarAction>; 10],
     hotbar_focus: Option<usize>,
+    /// Innate abilities that have already been offered for the hotbar.
+    known_abilities: Vec<Ability>,
 }
 
 impl GameRuntime {
@@ -33,6 +35,7 @@ impl GameRuntime {
             cursor_item: None,
             hotbar: Default::default(),
             hotbar_focus: None,
+            known_abilities: Vec::new(),
         }
     }
 
@@ -54,6 +57,35 @@ impl GameRuntime {
         true
     }
 
+    /// Pick up an item from the player's cell.
+    ///
+    /// If there are several items to choose from, switch to a menu for picking one.
+    fn pick_up(&mut self) -> Option<SceneSwitch<GameRuntime>> {
+        let items: Vec<Entity> = self
+            .world
+            .items_at(self.world.location(self.world.player()?)?)
+            .into_iter()
+            .filter(|&e| self.world.is_portable(e))
+            .collect();
+        match items.len() {
+            0 => None,
+            1 => {
+                self.command = Some(Command::Take(items[0]));
+                None
+            }
+            _ => Some(SceneSwitch::Push(Box::new(PickupScreen { items }))),
+        }
+    }
+
+    /// Open the first container the player can reach, preferring ones on the floor.
+    fn open_container(&self) -> Option<SceneSwitch<GameRuntime>> {
+        let container = *self
+            .world
+            .reachable_containers(self.world.player()?)
+            .first()?;
+        Some(SceneSwitch::Push(Box::new(ContainerScreen { container })))
+    }
+
     fn is_bindable_hotbar_action(&self, slot: usize) -> bool {
         match self.hotbar[slot] {
             Some(HotbarAction { ability, .. }) => ability.is_targeted(),
@@ -91,10 +123,16 @@ impl GameRuntime {
                         self.world.count(item),
                     );
                 }
-                Some(HotbarAction {
-                    ability: _ability, ..
-                }) => {
-                    // TODO: Icons for raw abilities
+                Some(HotbarAction { ability, .. }) => {
+                    // TODO: Icons for raw abilities, just show an abbreviation for now.
+                    let label: String = format!("{:?}", ability).chars().take(2).collect();
+                    canvas.draw_text(
+                        &*display::font(),
+                        pos + vec2(8, 4),
+                        Align::Center,
+                        color::YELLOW,
+                        &label,
+                    );
                 }
             }
 
@@ -150,6 +188,22 @@ impl GameRuntime {
                 }
             }
         }
+
+        // Put newly gained innate abilities in free hotbar slots.
+        if let Some(player) = self.world.player() {
+            for ability in self.world.list_abilities(player) {
+                if self.known_abilities.contains(&ability) {
+                    continue;
+                }
+                self.known_abilities.push(ability);
+                if let Some(slot) = self.hotbar.iter_mut().find(|a| a.is_none()) {
+                    *slot = Some(HotbarAction {
+                        ability,
+                        item: None,
+                    });
+                }
+            }
+        }
     }
 }
 
@@ -239,32 +293,32 @@ impl Scene<GameRuntime> for GameLoop {
         if view_area.contains(canvas.mouse_pos()) {
             let mouse_loc =
                 view.screen_to_cell(ScreenVector::from_untyped(canvas.mouse_pos().to_vector()));
-            (|| {
+            return (|| {
                 let player = ctx.world.player()?;
                 let relative_vec = ctx.world.location(player)?.v2_at(mouse_loc)?;
                 let click_state = canvas.click_state(&view_area);
 
                 if click_state == ButtonAction::LeftClicked {
                     if relative_vec == CellVector::zero() {
-                        ctx.command = Some(Command::Take);
+                        return ctx.pick_up();
                     } else {
                         let dir = Dir6::from_v2(relative_vec);
-                        self.smart_step(ctx, dir);
+                        return self.smart_step(ctx, dir);
                     }
                 }
 
-                // Use targeted ability with RMB
-                if click_state == ButtonAction::RightClicked {
+                // Use targeted ability with RMB, shoot if no ability is selected
+                if click_state == ButtonAction::RightClicked && relative_vec != CellVector::zero() {
+                    let dir = Dir6::from_v2(relative_vec);
                     if let Some(i) = ctx.hotbar_focus {
                         if let Some(HotbarAction { ability, item }) = ctx.hotbar[i] {
-                            if relative_vec != CellVector::zero() {
-                                let dir = Dir6::from_v2(relative_vec);
-                                ctx.command = Some(Command::TargetedAbility { ability, dir, item });
-                            }
+                            ctx.command = Some(Command::TargetedAbility { ability, dir, item });
                         }
+                    } else if ctx.world.can_command(&Command::Shoot(dir)) {
+                        ctx.command = Some(Command::Shoot(dir));
                     }
                 }
-                Some(())
+                None
             })();
         }
 
@@ -287,32 +341,35 @@ impl Scene<GameRuntime> for GameLoop {
 
             match scancode {
                 Q | Pad7 | Home => {
-                    self.smart_step(ctx, Dir6::Northwest);
+                    return self.smart_step(ctx, Dir6::Northwest);
                 }
                 W | Up | Pad8 => {
-                    self.smart_step(ctx, Dir6::North);
+                    return self.smart_step(ctx, Dir6::North);
                 }
                 E | Pad9 | PageUp => {
-                    self.smart_step(ctx, Dir6::Northeast);
+                    return self.smart_step(ctx, Dir6::Northeast);
                 }
                 A | Pad1 | End => {
-                    self.smart_step(ctx, Dir6::Southwest);
+                    return self.smart_step(ctx, Dir6::Southwest);
                 }
                 S | Down | Pad2 => {
-                    self.smart_step(ctx, Dir6::South);
+                    return self.smart_step(ctx, Dir6::South);
                 }
                 D | Pad3 | PageDown => {
-                    self.smart_step(ctx, Dir6::Southeast);
+                    return self.smart_step(ctx, Dir6::Southeast);
                 }
                 Left | Pad4 => {
-                    self.side_step(ctx, Side::West);
+                    return self.side_step(ctx, Side::West);
                 }
                 Right | Pad6 => {
-                    self.side_step(ctx, Side::East);
+                    return self.side_step(ctx, Side::East);
                 }
                 Space | Pad5 => {
                     ctx.command = Some(Command::Pass);
                 }
+                F => {
+                    ctx.command = Some(Command::Search);
+                }
 
                 // XXX: Wizard mode key, disable in legit gameplay mode
                 Backspace => {
@@ -332,7 +389,20 @@ impl Scene<GameRuntime> for GameLoop {
                 }
 
                 G => {
-                    ctx.command = Some(Command::Take);
+                    return ctx.pick_up();
+                }
+
+                O => {
+                    return ctx.open_container();
+                }
+
+                C => {
+                    // Close the first adjacent open door.
+                    if let Some(&dir) =
+                        Dir6::iter().find(|&&dir| ctx.world.can_command(&Command::Close(dir)))
+                    {
+                        ctx.command = Some(Command::Close(dir));
+                    }
                 }
 
                 Escape => {
@@ -379,7 +449,9 @@ impl Scene<GameRuntime> for GameLoop {
 
 impl GameLoop {
     /// Step command that turns into melee attack if an enemy is in the way.
-    fn smart_step(&self, ctx: &mut GameRuntime, dir: Dir6) -> ActionOutcome {
+    ///
+    /// Walking into a shopkeeper opens the shop instead.
+    fn smart_step(&self, ctx: &mut GameRuntime, dir: Dir6) -> Option<SceneSwitch<GameRuntime>> {
         let player = ctx.world.player()?;
         let loc = ctx.world.location(player)?;
 
@@ -402,8 +474,10 @@ impl GameLoop {
         let destination = loc.jump(&*ctx.world, dir);
 
         if let Some(mob) = ctx.world.mob_at(destination) {
-            if ctx.world.is_hostile_to(player, mob) {
-                // Fight on!
+            if ctx.world.can_trade_with(player, mob) {
+                return Some(SceneSwitch::Push(Box::new(ShopScreen { shopkeeper: mob })));
+            } else if !ctx.world.is_allied_to(player, mob) {
+                // Fight on! Neutral creatures get attacked too if you walk into them.
                 ctx.command = Some(Command::Melee(dir));
             } else {
                 // Do we want to do something smarter than walk into friendlies?
@@ -413,10 +487,10 @@ impl GameLoop {
         } else {
             ctx.command = Some(Command::Step(dir));
         }
-        Some(true)
+        None
     }
 
-    fn side_step(&self, ctx: &mut GameRuntime, side: Side) -> ActionOutcome {
+    fn side_step(&self, ctx: &mut GameRuntime, side: Side) -> Option<SceneSwitch<GameRuntime>> {
         let player = ctx.world.player()?;
         let loc = ctx.world.location(player)?;
         let flip = (loc.x + loc.y) % 2 == 0;
@@ -451,6 +525,32 @@ impl GameLoop {
             "Welcome to status bar",
         );
 
+        if let Some(player) = ctx.world.player() {
+            let mut labels = Vec::new();
+            match ctx.world.hunger(player) {
+                Hunger::Hungry => labels.push("Hungry".to_string()),
+                Hunger::Weak => labels.push("Weak".to_string()),
+                Hunger::Fainting => labels.push("Fainting".to_string()),
+                _ => {}
+            }
+            for (status, duration) in ctx.world.statuses(player) {
+                if status.is_visible() {
+                    // Show the remaining duration in turns.
+                    labels.push(format!("{:?} {}", status, (duration + 11) / 12));
+                }
+            }
+
+            if !labels.is_empty() {
+                canvas.draw_text(
+                    &*display::font(),
+                    area.origin + vec2(0, 10),
+                    Align::Left,
+                    color::YELLOW,
+                    &labels.join(" "),
+                );
+            }
+        }
+
         ctx.draw_hotbar(canvas);
     }
 
@@ -468,6 +568,7 @@ enum PickAction {
     Place(Entity),
     Swap(Entity, Entity),
     Drop(Entity),
+    DropOne(Entity),
 }
 
 impl Scene<GameRuntime> for InventoryScreen {
@@ -507,6 +608,9 @@ impl Scene<GameRuntime> for InventoryScreen {
                 Some(Drop(_e)) => {
                     ctx.force_command(Command::Drop(slot));
                 }
+                Some(DropOne(_e)) => {
+                    ctx.force_command(Command::DropCount(slot, 1));
+                }
                 _ => {}
             }
         }
@@ -609,6 +713,9 @@ impl InventoryScreen {
                     ctx.world.entity_icon(e).expect("Item icon missing"),
                     ctx.world.count(e),
                 );
+                if ctx.world.has_charges(e) {
+                    canvas.draw_item_charges(pos + vec2(8, 8), ctx.world.uses_left(e));
+                }
                 if canvas.click_state(&bounds) == ButtonAction::LeftClicked {
                     return match ctx.cursor_item {
                         None => Some(PickAction::Pick(e)),
@@ -620,6 +727,12 @@ impl InventoryScreen {
                         return Some(PickAction::Drop(e));
                     }
                 }
+                // Right click drops a single item from a stack.
+                if canvas.click_state(&bounds) == ButtonAction::RightClicked {
+                    if ctx.cursor_item.is_none() {
+                        return Some(PickAction::DropOne(e));
+                    }
+                }
             }
         }
 
@@ -632,3 +745,289 @@ impl InventoryScreen {
         None
     }
 }
+
+/// Menu for choosing which item to pick up when there are several on the floor.
+struct PickupScreen {
+    items: Vec<Entity>,
+}
+
+impl Scene<GameRuntime> for PickupScreen {
+    fn render(
+        &mut self,
+        ctx: &mut GameRuntime,
+        canvas: &mut Canvas,
+    ) -> Option<SceneSwitch<GameRuntime>> {
+        let width = canvas.screen_bounds().size.width;
+        canvas.draw_text(
+            &*display::font(),
+            point2(8, 8),
+            Align::Left,
+            color::YELLOW,
+            "Pick up which item?",
+        );
+
+        for (i, &item) in self.items.iter().enumerate().take(26) {
+            let pos = point2(8, 24 + i as i32 * 20);
+            let bounds = Rect::new(pos, size2(16, 16));
+            canvas.fill_rect(&bounds.inflate(1, 1), color::GREEN);
+            canvas.fill_rect(&bounds, color::BLACK);
+            canvas.draw_item_icon(
+                pos + vec2(8, 8),
+                ctx.world.entity_icon(item).expect("Item icon missing"),
+                ctx.world.count(item),
+            );
+
+            let letter = (b'a' + i as u8) as char;
+            canvas.draw_text(
+                &*display::font(),
+                pos + vec2(24, 4),
+                Align::Left,
+                color::SILVER,
+                &format!("{}) {}", letter, ctx.world.entity_name(item)),
+            );
+
+            let row = Rect::new(pos, size2(width - 16, 16));
+            if canvas.click_state(&row) == ButtonAction::LeftClicked {
+                return self.take(ctx, i);
+            }
+        }
+        None
+    }
+
+    fn input(
+        &mut self,
+        ctx: &mut GameRuntime,
+        event: &InputEvent,
+        _canvas: &mut Canvas,
+    ) -> Option<SceneSwitch<GameRuntime>> {
+        if let InputEvent::KeyEvent {
+            is_down: true,
+            hardware_key: Some(scancode),
+            ..
+        } = event
+        {
+            use Keycode::*;
+            match scancode {
+                Escape => return Some(SceneSwitch::Pop),
+                // Letter keys select items.
+                &key if (A as usize..=Z as usize).contains(&(key as usize)) => {
+                    return self.take(ctx, key as usize - A as usize);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl PickupScreen {
+    fn take(&self, ctx: &mut GameRuntime, idx: usize) -> Option<SceneSwitch<GameRuntime>> {
+        let &item = self.items.get(idx)?;
+        ctx.command = Some(Command::Take(item));
+        Some(SceneSwitch::Pop)
+    }
+}
+
+/// Screen for moving items between a container and the player's bag.
+struct ContainerScreen {
+    container: Entity,
+}
+
+impl Scene<GameRuntime> for ContainerScreen {
+    fn render(
+        &mut self,
+        ctx: &mut GameRuntime,
+        canvas: &mut Canvas,
+    ) -> Option<SceneSwitch<GameRuntime>> {
+        let player = match ctx.world.player() {
+            Some(player) if ctx.world.can_reach_container(player, self.container) => player,
+            // Container went out of reach or the player is gone.
+            _ => return Some(SceneSwitch::Pop),
+        };
+
+        canvas.draw_text(
+            &*display::font(),
+            point2(8, 8),
+            Align::Left,
+            color::YELLOW,
+            &format!("Contents of {}", ctx.world.entity_name(self.container)),
+        );
+
+        // Container items, click to take out.
+        let capacity = ctx.world.bag_capacity(self.container);
+        if let Some(slot) = bag_grid(ctx, canvas, point2(8, 24), self.container, capacity) {
+            if let Some(item) = ctx.world.entity_equipped(self.container, slot) {
+                ctx.force_command(Command::TakeOut(item));
+            }
+        }
+
+        // Player's bag, click to put in.
+        let rows = (capacity as i32 + 9) / 10;
+        let origin = point2(8, 40 + rows * 24);
+        let capacity = ctx.world.bag_capacity(player);
+        if let Some(slot) = bag_grid(ctx, canvas, origin, player, capacity) {
+            ctx.force_command(Command::PutIn(slot, self.container));
+        }
+        None
+    }
+
+    fn input(
+        &mut self,
+        _ctx: &mut GameRuntime,
+        event: &InputEvent,
+        _canvas: &mut Canvas,
+    ) -> Option<SceneSwitch<GameRuntime>> {
+        if let InputEvent::KeyEvent {
+            is_down: true,
+            hardware_key: Some(Keycode::Escape),
+            ..
+        } = event
+        {
+            return Some(SceneSwitch::Pop);
+        }
+        None
+    }
+}
+
+/// Screen for trading with a shopkeeper.
+struct ShopScreen {
+    shopkeeper: Entity,
+}
+
+impl Scene<GameRuntime> for ShopScreen {
+    fn render(
+        &mut self,
+        ctx: &mut GameRuntime,
+        canvas: &mut Canvas,
+    ) -> Option<SceneSwitch<GameRuntime>> {
+        let player = match ctx.world.player() {
+            Some(player) if ctx.world.can_trade_with(player, self.shopkeeper) => player,
+            // Walked away, shopkeeper got angry or the player is gone.
+            _ => return Some(SceneSwitch::Pop),
+        };
+
+        let width = canvas.screen_bounds().size.width;
+        canvas.draw_text(
+            &*display::font(),
+            point2(8, 8),
+            Align::Left,
+            color::YELLOW,
+            &format!(
+                "Buy from {} (right-click to steal), you have {} gold",
+                ctx.world.entity_name(self.shopkeeper),
+                ctx.world.gold(player)
+            ),
+        );
+
+        // Shop stock, click to buy.
+        let stock = ctx.world.shop_stock(self.shopkeeper);
+        for (i, &item) in stock.iter().enumerate() {
+            let pos = point2(8, 24 + i as i32 * 20);
+            let bounds = Rect::new(pos, size2(16, 16));
+            canvas.fill_rect(&bounds.inflate(1, 1), color::GREEN);
+            canvas.fill_rect(&bounds, color::BLACK);
+            canvas.draw_item_icon(
+                pos + vec2(8, 8),
+                ctx.world.entity_icon(item).expect("Item icon missing"),
+                ctx.world.count(item),
+            );
+
+            let price = ctx.world.buy_price(item);
+            let affordable = ctx.world.gold(player) >= price;
+            canvas.draw_text(
+                &*display::font(),
+                pos + vec2(24, 4),
+                Align::Left,
+                if affordable {
+                    color::SILVER
+                } else {
+                    color::GRAY
+                },
+                &format!("{} ({} gold)", ctx.world.entity_name(item), price),
+            );
+
+            let row = Rect::new(pos, size2(width - 16, 16));
+            match canvas.click_state(&row) {
+                ButtonAction::LeftClicked => {
+                    ctx.force_command(Command::Buy(item));
+                }
+                ButtonAction::RightClicked => {
+                    ctx.force_command(Command::Steal(item));
+                }
+                _ => {}
+            }
+        }
+
+        // Player's bag, click to sell.
+        let origin = point2(8, 40 + stock.len() as i32 * 20);
+        canvas.draw_text(
+            &*display::font(),
+            origin,
+            Align::Left,
+            color::YELLOW,
+            &format!(
+                "Sell, {} has {} gold",
+                ctx.world.entity_name(self.shopkeeper),
+                ctx.world.gold(self.shopkeeper)
+            ),
+        );
+        let capacity = ctx.world.bag_capacity(player);
+        if let Some(slot) = bag_grid(ctx, canvas, origin + vec2(0, 16), player, capacity) {
+            ctx.force_command(Command::Sell(slot, self.shopkeeper));
+        }
+        None
+    }
+
+    fn input(
+        &mut self,
+        _ctx: &mut GameRuntime,
+        event: &InputEvent,
+        _canvas: &mut Canvas,
+    ) -> Option<SceneSwitch<GameRuntime>> {
+        if let InputEvent::KeyEvent {
+            is_down: true,
+            hardware_key: Some(Keycode::Escape),
+            ..
+        } = event
+        {
+            return Some(SceneSwitch::Pop);
+        }
+        None
+    }
+}
+
+/// Draw the bag slots of an entity in the same grid layout as the inventory screen.
+///
+/// Return the slot of an item that was left-clicked.
+fn bag_grid(
+    ctx: &GameRuntime,
+    canvas: &mut Canvas,
+    origin: Point2D<i32>,
+    owner: Entity,
+    capacity: u32,
+) -> Option<Slot> {
+    let mut ret = None;
+    for i in 0..capacity {
+        let (x, y) = (i as i32 % 10, i as i32 / 10);
+        let pos = origin + vec2(x * 24, y * 24);
+        let bounds = Rect::new(pos, size2(16, 16));
+        canvas.fill_rect(&bounds.inflate(1, 1), color::GREEN);
+        canvas.fill_rect(&bounds, color::BLACK);
+
+        let slot = Slot::Bag(i);
+        if let Some(e) = ctx.world.entity_equipped(owner, slot) {
+            canvas.draw_item_icon(
+                pos + vec2(8, 8),
+                ctx.world.entity_icon(e).expect("Item icon missing"),
+                ctx.world.count(e),
+            );
+            if ctx.world.has_charges(e) {
+                canvas.draw_item_charges(pos + vec2(8, 8), ctx.world.uses_left(e));
+            }
+            if canvas.click_state(&bounds) == ButtonAction::LeftClicked {
+                ret = Some(slot);
+            }
+        }
+    }
+    ret
+}
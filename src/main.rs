@@ -4,6 +4,7 @@
 use crate::game_loop::GameLoop;
 use log::info;
 use rand::Rng;
+use std::path::PathBuf;
 use structopt::StructOpt;
 use vitral::{AppConfig, Flick};
 use world::{ExternalEntity, WorldSeed, WorldSkeleton};
@@ -15,6 +16,12 @@ mod msg;
 struct Opt {
     #[structopt(long = "seed")]
     seed: Option<u32>,
+    /// Directory with entity spec files to use instead of the built-in ones.
+    #[structopt(long = "specs", parse(from_os_str))]
+    specs: Option<PathBuf>,
+    /// How many sectors away from the player mobs keep moving.
+    #[structopt(long = "active-radius")]
+    active_radius: Option<i32>,
 }
 
 pub fn main() {
@@ -23,6 +30,13 @@ pub fn main() {
     const FPS: f64 = 30.0;
     env_logger::init();
 
+    if let Some(dir) = &opt.specs {
+        if let Err(e) = world::load_specs(dir) {
+            eprintln!("Error loading specs: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     display::load_graphics();
 
     msg::register();
@@ -35,6 +49,7 @@ pub fn main() {
         rng_seed,
         world_skeleton: WorldSkeleton::overworld_sprawl(),
         player_character: ExternalEntity::from_name("player").unwrap(),
+        active_radius: opt.active_radius.unwrap_or(world::DEFAULT_ACTIVE_RADIUS),
     };
 
     vitral::App::new(
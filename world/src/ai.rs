@@ -2,13 +2,19 @@
 
 use crate::{
     msg,
+    sound::Noise,
     stats::{Intrinsic, Status},
-    Location, World,
+    Ability, Location, Sector, World,
 };
 use calx::{Dir6, RngExt};
 use calx_ecs::Entity;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+/// Distance at which mobs give up on their target and fleeing mobs feel safe.
+const FLEE_THRESHOLD: i32 = 14;
+
 /// Used to determine who tries to fight whom.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Alignment {
@@ -18,6 +24,35 @@ pub enum Alignment {
     Player,
     /// Indifferent or hungry, potential threat to Player and Enemy alike
     Animal,
+    /// Townsfolk who won't fight anyone unless provoked.
+    Peaceful,
+}
+
+/// How members of one faction regard members of another.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Attitude {
+    /// Will attack on sight.
+    Hostile,
+    /// Leaves the other alone unless given a reason not to.
+    Neutral,
+    /// On the same side.
+    Allied,
+}
+
+impl Alignment {
+    /// Faction relationship table.
+    pub fn attitude_towards(self, other: Alignment) -> Attitude {
+        use Alignment::*;
+        match (self, other) {
+            (Enemy, Enemy) | (Player, Player) | (Animal, Animal) | (Peaceful, Peaceful) => {
+                Attitude::Allied
+            }
+            (Enemy, Player) | (Player, Enemy) => Attitude::Hostile,
+            // Animals mind their own business until they're attacked or get hungry.
+            (Animal, _) | (_, Animal) => Attitude::Neutral,
+            (Peaceful, _) | (_, Peaceful) => Attitude::Neutral,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -25,6 +60,9 @@ pub struct Brain {
     pub state: BrainState,
     pub alignment: Alignment,
     pub shout: ShoutType,
+    /// Animal is on the prowl and will go after anything it isn't allied with.
+    #[serde(default)]
+    pub hungry: bool,
 }
 
 impl Brain {
@@ -48,6 +86,11 @@ impl Brain {
         self.shout = shout;
         self
     }
+
+    pub fn alignment(mut self, alignment: Alignment) -> Brain {
+        self.alignment = alignment;
+        self
+    }
 }
 
 impl Default for Brain {
@@ -56,6 +99,7 @@ impl Default for Brain {
             state: BrainState::Asleep,
             alignment: Alignment::Enemy,
             shout: ShoutType::Silent,
+            hungry: false,
         }
     }
 }
@@ -68,6 +112,10 @@ pub enum BrainState {
     Asleep,
     /// AI mob is looking for a fight.
     Hunting(Entity),
+    /// AI mob is hurt and running away from a threat.
+    Fleeing(Entity),
+    /// AI mob heard something and is going to check out the location.
+    Investigating(Location),
     /// Mob is wandering aimlessly
     Roaming,
     /// Mob is under player control.
@@ -112,35 +160,75 @@ impl World {
     fn run_ai_for(&mut self, npc: Entity) {
         use BrainState::*;
 
-        // Prevent hyperactive mobs from ever being in asleep state.
-        if self.has_intrinsic(npc, Intrinsic::Hyperactive) {
-            if let Some(brain) = self.ecs_mut().brain.get_mut(npc) {
-                if brain.state == BrainState::Asleep {
-                    brain.state = BrainState::Roaming;
-                }
-            }
+        // Prevent hyperactive mobs from ever being in asleep state. Followers also stay awake to
+        // keep up with their leader.
+        if (self.has_intrinsic(npc, Intrinsic::Hyperactive) || self.leader(npc).is_some())
+            && self.brain_state(npc) == Some(Asleep)
+        {
+            self.set_brain_state(npc, Roaming);
         }
 
         let brain_state = self.brain_state(npc).expect("Running AI for non-mob");
+
+        // Shopkeepers stay behind the counter until someone gives them trouble.
+        if self.has_intrinsic(npc, Intrinsic::Shopkeeper)
+            && !matches!(brain_state, Hunting(_) | Fleeing(_))
+        {
+            let _ = self.idle(npc);
+            return;
+        }
+
         match brain_state {
             Asleep => {
-                // TODO: Noise waves that wake up sleeping mobs when they hit them, stop making
-                // each sleeping mob do active scanning every tic.
-                if let Some(e) = self.find_enemy(npc) {
-                    self.designate_enemy(npc, e);
-                }
+                // Sleeping mobs don't look around, they're woken up by noise.
             }
             Hunting(target) => {
-                if self.rng().one_chance_in(12) {
+                if !self.is_alive(target) {
+                    // Target is gone, go back to looking around.
+                    self.set_brain_state(npc, Roaming);
+                    self.ai_drift(npc);
+                } else if self.morale_breaks(npc) {
+                    if self.player_notices(npc) {
+                        msg!("[One] turn[s] to flee."; self.subject(npc));
+                    }
+                    self.set_brain_state(npc, Fleeing(target));
+                    self.ai_flee(npc, target);
+                } else if self.strayed_from_leader(npc) {
+                    // Don't chase enemies too far from the leader.
+                    self.set_brain_state(npc, Roaming);
+                    self.ai_follow(npc);
+                } else if self.rng().one_chance_in(12) || !self.can_sense(npc, target) {
                     self.ai_drift(npc);
                 } else {
                     self.ai_hunt(npc, target);
                 }
             }
+            Fleeing(threat) => {
+                if !self.is_alive(threat) {
+                    self.set_brain_state(npc, Roaming);
+                    self.ai_drift(npc);
+                } else if self.hp(npc) * 3 >= self.max_hp(npc) * 2 {
+                    // Healed up enough to go back to the fight.
+                    self.set_brain_state(npc, Hunting(threat));
+                    self.ai_hunt(npc, threat);
+                } else {
+                    self.ai_flee(npc, threat);
+                }
+            }
+            Investigating(loc) => {
+                if let Some(e) = self.find_enemy(npc) {
+                    self.designate_enemy(npc, e);
+                    self.ai_hunt(npc, e);
+                } else {
+                    self.ai_investigate(npc, loc);
+                }
+            }
             Roaming => {
                 if let Some(e) = self.find_enemy(npc) {
                     self.designate_enemy(npc, e);
                     self.ai_hunt(npc, e);
+                } else if self.leader(npc).is_some() {
+                    self.ai_follow(npc);
                 } else {
                     self.ai_drift(npc);
                 }
@@ -154,7 +242,11 @@ impl World {
         if let (Some(my_loc), Some(target_loc)) = (self.location(npc), self.location(target)) {
             if my_loc.metric_distance(target_loc) == 1 {
                 let _ = self.entity_melee(npc, my_loc.dir6_towards(target_loc).unwrap());
-            } else if let Some(move_dir) = self.pathing_dir_towards(npc, target_loc) {
+            } else if let Some((ability, dir)) = self.ai_targeted_ability(npc, target) {
+                let _ = self.use_targeted_ability(npc, ability, dir);
+            } else if let Some(dir) = self.ranged_attack_dir(npc, target) {
+                let _ = self.entity_shoot(npc, dir);
+            } else if let Some(move_dir) = self.pathing_dir_to_entity(npc, target) {
                 let _ = self.entity_step(npc, move_dir);
             } else {
                 self.ai_drift(npc);
@@ -162,6 +254,110 @@ impl World {
         }
     }
 
+    /// Run away from a threat.
+    ///
+    /// Rest once far enough away, fight back if cornered.
+    fn ai_flee(&mut self, npc: Entity, threat: Entity) {
+        let safe = !self.can_sense(npc, threat)
+            || !matches!(self.distance_between(npc, threat), Some(d) if d <= FLEE_THRESHOLD);
+        if safe {
+            let _ = self.idle(npc);
+            return;
+        }
+
+        if let Some(dir) = self.pathing_dir_away_from_entity(npc, threat) {
+            if self.entity_step(npc, dir).is_some() {
+                return;
+            }
+        }
+
+        // Nowhere left to run.
+        self.set_brain_state(npc, BrainState::Hunting(threat));
+        self.ai_hunt(npc, threat);
+    }
+
+    /// Check whether a wounded mob loses its nerve.
+    ///
+    /// Mobs start getting scared when they're down to a third of their health, and the chance of
+    /// fleeing grows the more hurt they are. Mindless mobs never flee.
+    fn morale_breaks(&mut self, npc: Entity) -> bool {
+        if self.has_intrinsic(npc, Intrinsic::Mindless) {
+            return false;
+        }
+        let (hp, max_hp) = (self.hp(npc), self.max_hp(npc));
+        if hp * 3 >= max_hp {
+            return false;
+        }
+        self.rng().gen_range(0..max_hp) >= hp * 3
+    }
+
+    /// Decide whether to use a targeted innate ability against target.
+    fn ai_targeted_ability(&mut self, npc: Entity, target: Entity) -> Option<(Ability, Dir6)> {
+        // Keep spellcasting occasional so spellcasters don't bombard the player every turn.
+        const CAST_CHANCE: u32 = 3;
+        const CAST_RANGE: usize = 9;
+        // Stay out of blast radius of area spells.
+        const MIN_DISTANCE: i32 = 3;
+
+        let abilities: Vec<Ability> = self
+            .list_abilities(npc)
+            .into_iter()
+            .filter(|a| a.is_targeted())
+            .collect();
+        if abilities.is_empty() {
+            return None;
+        }
+
+        let (my_loc, target_loc) = (self.location(npc)?, self.location(target)?);
+        if my_loc.metric_distance(target_loc) < MIN_DISTANCE {
+            return None;
+        }
+        let dir = my_loc.dir6_towards(target_loc)?;
+        if self.find_ranged_target(npc, dir, CAST_RANGE) != Some(target) {
+            return None;
+        }
+        if !self.rng().one_chance_in(CAST_CHANCE) {
+            return None;
+        }
+
+        Some((*abilities.choose(self.rng())?, dir))
+    }
+
+    /// Return the direction to shoot at target if the mob has a clear line of fire.
+    fn ranged_attack_dir(&self, npc: Entity, target: Entity) -> Option<Dir6> {
+        let range = self.stats(npc).ranged_range;
+        if range == 0 {
+            return None;
+        }
+        let dir = self.location(npc)?.dir6_towards(self.location(target)?)?;
+        if self.find_ranged_target(npc, dir, range as usize) == Some(target) {
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
+    /// Go to a location to see what's there.
+    ///
+    /// Give up and start roaming once the location is reached or can't be approached.
+    fn ai_investigate(&mut self, npc: Entity, loc: Location) {
+        let arrived = match self.location(npc) {
+            Some(my_loc) => my_loc.metric_distance(loc) <= 1,
+            None => true,
+        };
+
+        if !arrived {
+            if let Some(move_dir) = self.pathing_dir_towards(npc, loc) {
+                if self.entity_step(npc, move_dir).is_some() {
+                    return;
+                }
+            }
+        }
+
+        self.set_brain_state(npc, BrainState::Roaming);
+        self.ai_drift(npc);
+    }
+
     /// Wander around aimlessly
     fn ai_drift(&mut self, npc: Entity) {
         let dirs = Dir6::permuted_dirs(self.rng());
@@ -172,15 +368,71 @@ impl World {
         }
     }
 
+    /// Stay close to the leader of the mob.
+    ///
+    /// Mill about near the leader when already close enough.
+    fn ai_follow(&mut self, npc: Entity) {
+        const FOLLOW_DISTANCE: i32 = 2;
+
+        let leader = match self.leader(npc) {
+            Some(leader) => leader,
+            None => return,
+        };
+        let leader_loc = match self.location(leader) {
+            Some(loc) => loc,
+            None => return,
+        };
+
+        match self.distance_between(npc, leader) {
+            Some(d) if d <= FOLLOW_DISTANCE => {
+                let origin = self.location(npc).unwrap();
+                let dirs = Dir6::permuted_dirs(self.rng());
+                for &dir in &dirs {
+                    let loc = origin.jump(self, dir);
+                    if loc.metric_distance(leader_loc) <= FOLLOW_DISTANCE
+                        && self.entity_step(npc, dir).is_some()
+                    {
+                        return;
+                    }
+                }
+                let _ = self.idle(npc);
+            }
+            Some(_) => {
+                if let Some(dir) = self.pathing_dir_to_entity(npc, leader) {
+                    let _ = self.entity_step(npc, dir);
+                } else {
+                    self.ai_drift(npc);
+                }
+            }
+            // Leader is somewhere out of reach.
+            None => self.ai_drift(npc),
+        }
+    }
+
+    /// Return whether a follower has wandered too far from its leader.
+    fn strayed_from_leader(&self, npc: Entity) -> bool {
+        const LEASH_DISTANCE: i32 = 8;
+
+        match self.leader(npc) {
+            Some(leader) => {
+                matches!(self.distance_between(npc, leader), Some(d) if d > LEASH_DISTANCE)
+            }
+            None => false,
+        }
+    }
+
     /// Find for an enemy for AI to target
-    fn find_enemy(&self, npc: Entity) -> Option<Entity> {
-        static FLEE_THRESHOLD: i32 = 14;
+    ///
+    /// Picks the nearest hostile creature the mob can see. Hungry animals go after anything they
+    /// aren't allied with.
+    fn find_enemy(&mut self, npc: Entity) -> Option<Entity> {
         const WAKEUP_DISTANCE: i32 = 5;
 
         let brain_state = self.brain_state(npc)?;
         if let BrainState::Hunting(x) = brain_state {
             // Is the existing target still valid?
             if self.is_alive(x)
+                && self.can_sense(npc, x)
                 && self
                     .distance_between(npc, x)
                     .map_or(false, |d| d <= FLEE_THRESHOLD)
@@ -189,29 +441,26 @@ impl World {
             }
         }
 
-        // TODO: Possibility to pick other targets than player
-        // TODO: Creatures that are friendly or neutral to player
-
-        if let (Some(loc), Some(player), Some(player_loc)) = (
-            self.location(npc),
-            self.player(),
-            self.player().map(|p| self.location(p)).unwrap_or(None),
-        ) {
-            if self.player_sees(loc) {
-                // Okay, tricky spot. Player might be seeing mob across a portal, in
-                // which case we can't do naive distance check.
-                // This could have a helper method that finds chart distance to self in
-                // player's map memory.
-                //
-                // For now, let's just go with mobs past portals not waking up to
-                // player.
-
-                if loc.metric_distance(player_loc) <= WAKEUP_DISTANCE {
-                    return Some(player);
-                }
-            }
-        }
-        None
+        let loc = self.location(npc)?;
+        let hungry = self.ecs().brain.get(npc).is_some_and(|b| b.hungry);
+
+        // FOV is only computed from the mob's position, so there's no need to worry about
+        // targets seen across portals being farther away than they look.
+        let mut targets: Vec<Entity> = self
+            .fov_from(loc, WAKEUP_DISTANCE)
+            .into_iter()
+            .filter_map(|loc| self.mob_at(loc))
+            .filter(|&e| {
+                e != npc
+                    && self.can_sense(npc, e)
+                    && (self.is_hostile_to(npc, e)
+                        || (hungry && self.attitude(npc, e) != Some(Attitude::Allied)))
+            })
+            .collect();
+        // Stable sort, FOV order breaks ties deterministically.
+        targets.sort_by_key(|&e| self.distance_between(npc, e).unwrap_or(i32::MAX));
+
+        targets.first().cloned()
     }
 
     /// End move for entity.
@@ -220,31 +469,88 @@ impl World {
     pub(crate) fn end_turn(&mut self, e: Entity) {
         let delay = self.action_delay(e);
         self.gain_status(e, Status::Delayed, delay);
+        self.consume_nutrition(e);
     }
 
     pub(crate) fn notify_attacked_by(&mut self, victim: Entity, attacker: Entity) {
         // TODO: Check if victim is already in close combat and don't disengage against new target
         // if it is.
-        // TODO: The idea is to do Doom-style thing where friendly fire will occasionally cause
-        // infighting between enemies, but it should be rare.
+
+        // Doom-style infighting, friendly fire will occasionally make allies turn on each other.
+        // Hitting back at an ally who has already turned on you is always fine.
+        const INFIGHTING_CHANCE: u32 = 4;
+        if self.attitude(victim, attacker) == Some(Attitude::Allied)
+            && !self.is_hostile_to(attacker, victim)
+            && !self.rng().one_chance_in(INFIGHTING_CHANCE)
+        {
+            // Shrug it off, but wake up if sleeping.
+            if self.brain_state(victim) == Some(BrainState::Asleep) {
+                if let Some(loc) = self.location(attacker) {
+                    self.investigate(victim, loc);
+                }
+            }
+            return;
+        }
+
         self.designate_enemy(victim, attacker);
     }
 
+    /// Turn a mob into a follower of the leader.
+    pub(crate) fn make_ally(&mut self, e: Entity, leader: Entity) {
+        if !self.is_npc(e) {
+            return;
+        }
+        let alignment = match self.alignment(leader) {
+            Some(a) => a,
+            None => return,
+        };
+        if let Some(brain) = self.ecs_mut().brain.get_mut(e) {
+            brain.alignment = alignment;
+        }
+        self.set_brain_state(e, BrainState::Roaming);
+    }
+
+    /// Turn a mob against the player side for good.
+    ///
+    /// Unlike just being attacked, the grudge outlasts the mob losing track of the offender.
+    pub(crate) fn make_hostile(&mut self, e: Entity, offender: Entity) {
+        if !self.is_npc(e) {
+            return;
+        }
+        if let Some(brain) = self.ecs_mut().brain.get_mut(e) {
+            brain.alignment = Alignment::Enemy;
+        }
+        self.designate_enemy(e, offender);
+    }
+
     fn designate_enemy(&mut self, e: Entity, target: Entity) {
         // TODO: Probably want this logic to be more complex eventually.
         if self.is_npc(e) {
-            if self.brain_state(e) == Some(BrainState::Asleep) {
-                self.shout(e);
-            }
-            if let Some(brain) = self.ecs_mut().brain.get_mut(e) {
-                brain.state = BrainState::Hunting(target);
+            match self.brain_state(e) {
+                Some(BrainState::Asleep) | Some(BrainState::Investigating(_)) => self.shout(e),
+                _ => {}
             }
+            // Fleeing mobs keep running, now from the new attacker.
+            let state = match self.brain_state(e) {
+                Some(BrainState::Fleeing(_)) => BrainState::Fleeing(target),
+                _ => BrainState::Hunting(target),
+            };
+            self.set_brain_state(e, state);
         }
     }
 
+    /// Make a mob wake up and go check out a location.
+    pub(crate) fn investigate(&mut self, e: Entity, loc: Location) {
+        if !self.is_npc(e) {
+            return;
+        }
+        self.set_brain_state(e, BrainState::Investigating(loc));
+    }
+
     /// Make a mob shout according to its type.
+    ///
+    /// The shout makes noise that may wake up other nearby monsters.
     pub(crate) fn shout(&mut self, e: Entity) {
-        // TODO: Create noise, wake up other nearby monsters.
         if let Some(shout) = self.ecs().brain.get(e).map(|b| b.shout) {
             match shout {
                 ShoutType::Shout => {
@@ -273,6 +579,10 @@ impl World {
                 }
                 ShoutType::Silent => {}
             }
+
+            if let Some(loc) = self.location(e) {
+                self.make_noise(loc, Noise::Shout(shout), Some(e));
+            }
         }
     }
 
@@ -280,15 +590,55 @@ impl World {
     pub fn is_mob(&self, e: Entity) -> bool { self.ecs().brain.contains(e) }
 
     /// Return the AI state of an entity.
-    fn brain_state(&self, e: Entity) -> Option<BrainState> {
+    pub(crate) fn brain_state(&self, e: Entity) -> Option<BrainState> {
         self.ecs().brain.get(e).map(|brain| brain.state)
     }
 
+    /// Change the AI state of a mob.
+    ///
+    /// Animals decide whether they're hungry every time their state changes.
+    pub(crate) fn set_brain_state(&mut self, e: Entity, state: BrainState) {
+        const ANIMAL_HUNGER_CHANCE: u32 = 20;
+
+        let changed = match self.brain_state(e) {
+            Some(old) => old != state,
+            None => return,
+        };
+        let hungry = if changed && self.alignment(e) == Some(Alignment::Animal) {
+            Some(self.rng().one_chance_in(ANIMAL_HUNGER_CHANCE))
+        } else {
+            None
+        };
+
+        let brain = &mut self.ecs_mut().brain[e];
+        brain.state = state;
+        if let Some(hungry) = hungry {
+            brain.hungry = hungry;
+        }
+    }
+
     /// Return the value for how a mob will react to other mobs.
     pub fn alignment(&self, e: Entity) -> Option<Alignment> {
         self.ecs().brain.get(e).map(|b| b.alignment)
     }
 
+    /// Return the creature an allied mob follows around.
+    ///
+    /// Currently only the player can lead allies.
+    pub fn leader(&self, e: Entity) -> Option<Entity> {
+        let player = self.player()?;
+        if self.is_npc(e) && self.is_allied_to(e, player) {
+            Some(player)
+        } else {
+            None
+        }
+    }
+
+    /// Return how a mob regards another mob based on their factions.
+    pub fn attitude(&self, e: Entity, other: Entity) -> Option<Attitude> {
+        Some(self.alignment(e)?.attitude_towards(self.alignment(other)?))
+    }
+
     /// Return how many frames the entity will delay after an action.
     pub(crate) fn action_delay(&self, e: Entity) -> u32 {
         // Granular speed system:
@@ -330,7 +680,7 @@ impl World {
             return false;
         }
 
-        if self.has_status(e, Status::Delayed) {
+        if self.has_status(e, Status::Delayed) || self.has_status(e, Status::Stunned) {
             return false;
         }
 
@@ -374,6 +724,13 @@ impl World {
         }
     }
 
+    /// Return whether a mob can perceive another entity.
+    ///
+    /// Invisible entities can only be felt when they're right next to you.
+    pub fn can_sense(&self, e: Entity, other: Entity) -> bool {
+        !self.has_status(other, Status::Invisible) || self.distance_between(e, other) == Some(1)
+    }
+
     /// Return whether the entity wants to fight the other entity.
     pub fn is_hostile_to(&self, npc: Entity, other: Entity) -> bool {
         if !self.is_alive(other) {
@@ -382,45 +739,38 @@ impl World {
         }
 
         if let Some(BrainState::Hunting(target)) = self.brain_state(npc) {
-            if npc == target {
+            if other == target {
                 // Already beating him up, obviously he must've done something bad to make you
                 // fight him.
                 return true;
             }
         }
 
-        let (a, b) = (self.alignment(npc), self.alignment(other));
-        if a.is_none() || b.is_none() {
-            return false;
-        }
+        self.attitude(npc, other) == Some(Attitude::Hostile)
+    }
 
-        a != b
+    /// Return whether the entity is on the same side as the other entity.
+    pub fn is_allied_to(&self, e: Entity, other: Entity) -> bool {
+        self.attitude(e, other) == Some(Attitude::Allied)
     }
 
     /// Look for targets to shoot in a direction.
+    ///
+    /// Returns the entity a projectile fired in the direction would hit if it's hostile to the
+    /// shooter.
     pub fn find_ranged_target(&self, shooter: Entity, dir: Dir6, range: usize) -> Option<Entity> {
-        let origin = self.location(shooter).unwrap();
-        let mut loc = origin;
-        for _ in 1..=range {
-            loc = loc.jump(self, dir);
-            if self.terrain(loc).blocks_shot() {
-                break;
-            }
-            if let Some(e) = self.mob_at(loc) {
-                if self.is_hostile_to(shooter, e) {
-                    return Some(e);
-                }
-            }
-        }
-        None
+        let origin = self.location(shooter)?;
+        let end = self.projected_explosion_center(origin, dir, range as u32);
+        self.mob_at(end)
+            .filter(|&e| e != shooter && self.is_hostile_to(shooter, e))
     }
 
     /// Try to get the next step on the path from origin towards destination.
     ///
     /// Tries to be fast, not necessarily doing proper pathfinding.
     pub(crate) fn pathing_dir_towards(&self, e: Entity, destination: Location) -> Option<Dir6> {
-        // Could do all sorts of cool things here eventually like a Dijkstra map cache, but for now
-        // just doing very simple stuff.
+        // Chasing specific entities is handled by the Dijkstra maps in `pathing_dir_to_entity`,
+        // this is just a simple fallback for arbitrary destinations.
         if let Some(origin) = self.location(e) {
             if let Some(dir) = origin.dir6_towards(destination) {
                 // Try direct approach, the the other directions.
@@ -442,11 +792,120 @@ impl World {
 
     /// Return the set of mobs that are in update range.
     ///
-    /// In a large game world, the active set is limited to the player's surroundings.
+    /// In a large game world, the active set is limited to the player's surroundings. Mobs more
+    /// than `active_radius` sectors away from the player's sector or on a different floor are
+    /// frozen until the player comes closer. Without a player, every mob is active.
     pub fn active_mobs(&self) -> Vec<Entity> {
+        let center = self
+            .player()
+            .and_then(|p| self.location(p))
+            .map(Sector::from);
+        let radius = self.flags.active_radius;
+
         self.entities()
             .filter(|&&e| self.is_mob(e))
+            .filter(|&&e| match center {
+                Some(center) => matches!(
+                    self.location(e).and_then(|loc| center.distance_from(Sector::from(loc))),
+                    Some(d) if d <= radius
+                ),
+                None => true,
+            })
             .cloned()
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::BrainState;
+    use crate::{
+        stats::Status,
+        test_util::{test_world, wait_for_turn},
+        Command, ExternalEntity, Sector, Terrain,
+    };
+    use calx::{Dir6, Incremental};
+
+    #[test]
+    fn test_active_mobs() {
+        let mut world = test_world();
+        world.flags.active_radius = 0;
+        let player = world.player().unwrap();
+        let origin = world.location(player).unwrap();
+
+        // Find a spot just past the edge of the player's sector.
+        let mut outside = origin;
+        while Sector::from(outside) == Sector::from(origin) {
+            outside = outside.jump(&world, Dir6::North);
+        }
+        world.set_terrain(outside, Terrain::Ground);
+        let inside = origin.jump(&world, Dir6::South);
+        world.set_terrain(inside, Terrain::Ground);
+
+        let rat = world.spawn(&ExternalEntity::from_name("rat").unwrap(), outside);
+        world.set_brain_state(rat, BrainState::Asleep);
+        world.gain_status(rat, Status::Poisoned, 100);
+        let hp = world.hp(rat);
+
+        // Frozen rats don't feel the poison.
+        assert!(!world.active_mobs().contains(&rat));
+        for _ in 0..10 {
+            world.update(&Command::Pass);
+            wait_for_turn(&mut world);
+        }
+        assert_eq!(world.hp(rat), hp);
+
+        world.place_entity(rat, inside);
+        assert!(world.active_mobs().contains(&rat));
+        for _ in 0..10 {
+            world.update(&Command::Pass);
+            wait_for_turn(&mut world);
+        }
+        assert!(world.hp(rat) < hp);
+    }
+
+    #[test]
+    fn test_animal_hunger() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+        let next = Dir6::iter()
+            .map(|&dir| loc.jump(&world, dir))
+            .find(|&loc| world.can_enter(player, loc))
+            .unwrap();
+
+        let rat = world.spawn(&ExternalEntity::from_name("rat").unwrap(), next);
+        world.set_brain_state(rat, BrainState::Roaming);
+        world.ecs_mut().brain[rat].hungry = false;
+
+        // A fed animal leaves you alone no matter how long it looks at you.
+        for _ in 0..100 {
+            assert_eq!(world.find_enemy(rat), None);
+        }
+
+        world.ecs_mut().brain[rat].hungry = true;
+        assert_eq!(world.find_enemy(rat), Some(player));
+    }
+
+    #[test]
+    fn test_ranged_ai() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        world.ecs_mut().stats[player].base.base_power = 100;
+        world.rebuild_stats(player);
+
+        let mut loc = world.location(player).unwrap();
+        for _ in 0..3 {
+            loc = loc.jump(&world, Dir6::North);
+            world.set_terrain(loc, Terrain::Ground);
+        }
+        let centaur = world.spawn(&ExternalEntity::from_name("centaur").unwrap(), loc);
+        world.ecs_mut().stats[centaur].base.base_attack = 1000;
+        world.rebuild_stats(centaur);
+
+        // The centaur shoots from where it stands instead of closing in.
+        world.ai_hunt(centaur, player);
+        assert!(world.hp(player) < world.max_hp(player));
+        assert_eq!(world.location(centaur), Some(loc));
+    }
+}
@@ -104,6 +104,8 @@ pub enum AnimState {
     Explosion,
     /// Pre-exploded fireball
     Firespell,
+    /// Missile from a ranged attack
+    Projectile,
 }
 
 impl AnimState {
@@ -113,7 +115,7 @@ impl AnimState {
         use AnimState::*;
         match self {
             Mob | MobHurt | MobBlocks => false,
-            Gib | Smoke | Explosion | Firespell => true,
+            Gib | Smoke | Explosion | Firespell | Projectile => true,
         }
     }
 }
@@ -18,16 +18,22 @@ pub enum Command {
     Wait,
     /// Do nothing, skip your turn.
     Pass,
+    /// Spend your turn looking for hidden traps.
+    Search,
     /// Take a step in direction.
     Step(Dir6),
     /// Melee attack in direction.
     Melee(Dir6),
-    /// Pick up the topmost item from the floor where you're standing on.
-    ///
-    /// TODO: Item selection support.
-    Take,
+    /// Fire a ranged attack in direction.
+    Shoot(Dir6),
+    /// Close an open door in direction.
+    Close(Dir6),
+    /// Pick up an item from the floor where you're standing on.
+    Take(Entity),
     /// Drop an item from inventory slot.
     Drop(Slot),
+    /// Drop some of the items in a stack in inventory slot.
+    DropCount(Slot, u32),
     /// Equip or unequip an item in slot.
     ///
     /// Items in equipment slots are unequipped to inventory. Items in inventory slots are equipped
@@ -37,6 +43,16 @@ pub enum Command {
     InventoryPlace(Entity, Slot),
     /// Swap two slotted items in inventory.
     InventorySwap(Slot, Slot),
+    /// Put an item from inventory slot into a container.
+    PutIn(Slot, Entity),
+    /// Take an item out of a container.
+    TakeOut(Entity),
+    /// Buy an item from a shopkeeper.
+    Buy(Entity),
+    /// Sell the item in inventory slot to a shopkeeper.
+    Sell(Slot, Entity),
+    /// Take an item from a shopkeeper without paying.
+    Steal(Entity),
     /// Use an undirected action that may be invoked via an item.
     UntargetedAbility {
         ability: Ability,
@@ -93,7 +109,7 @@ impl World {
                 if !slot.accepts(self.equip_type(*item)) {
                     return false;
                 }
-                true
+                self.can_move_item(player, *item, *slot)
             }
 
             InventorySwap(slot1, slot2) => {
@@ -101,19 +117,90 @@ impl World {
                     return false;
                 }
                 if let Some(e) = self.entity_equipped(player, *slot1) {
-                    if !slot2.accepts(self.equip_type(e)) {
+                    if !slot2.accepts(self.equip_type(e)) || !self.can_move_item(player, e, *slot2)
+                    {
                         return false;
                     }
                 }
 
                 if let Some(e) = self.entity_equipped(player, *slot2) {
-                    if !slot1.accepts(self.equip_type(e)) {
+                    if !slot1.accepts(self.equip_type(e)) || !self.can_move_item(player, e, *slot1)
+                    {
                         return false;
                     }
                 }
                 true
             }
 
+            Equip(slot) => match self.entity_equipped(player, *slot) {
+                Some(item) if slot.is_equipment_slot() => self.can_unequip(item),
+                Some(item) => self.meets_requirements(player, item),
+                None => false,
+            },
+
+            Drop(slot) => match self.entity_equipped(player, *slot) {
+                Some(item) => self.can_unequip(item),
+                None => false,
+            },
+
+            DropCount(slot, n) => match self.entity_equipped(player, *slot) {
+                Some(item) => *n > 0 && *n <= self.count(item) && self.can_unequip(item),
+                None => false,
+            },
+
+            Take(item) => match self.location(player) {
+                Some(loc) => self.items_at(loc).contains(item) && self.is_portable(*item),
+                None => false,
+            },
+
+            PutIn(slot, container) => match self.entity_equipped(player, *slot) {
+                // Containers don't go inside other containers.
+                Some(item) => {
+                    !self.is_container(item)
+                        && self.can_unequip(item)
+                        && self.can_reach_container(player, *container)
+                        && self.free_bag_slot(*container).is_some()
+                }
+                None => false,
+            },
+
+            TakeOut(item) => match self.entity_parent(*item) {
+                Some(container) => self.can_reach_container(player, container),
+                None => false,
+            },
+
+            Buy(item) => match self.seller_of(*item) {
+                Some(shopkeeper) => {
+                    self.can_trade_with(player, shopkeeper)
+                        && self.gold(player) >= self.buy_price(*item)
+                        && self.free_bag_slot(player).is_some()
+                }
+                None => false,
+            },
+
+            Sell(slot, shopkeeper) => match self.entity_equipped(player, *slot) {
+                Some(item) => {
+                    !self.is_currency(item)
+                        && self.can_unequip(item)
+                        && self.sell_price(item) > 0
+                        && self.gold(*shopkeeper) >= self.sell_price(item)
+                        && self.can_trade_with(player, *shopkeeper)
+                        && self.free_bag_slot(*shopkeeper).is_some()
+                }
+                None => false,
+            },
+
+            Steal(item) => match self.seller_of(*item) {
+                Some(shopkeeper) => {
+                    self.can_trade_with(player, shopkeeper) && self.free_bag_slot(player).is_some()
+                }
+                None => false,
+            },
+
+            Shoot(_) => self.stats(player).ranged_range > 0,
+
+            Close(dir) => self.can_close(player, *dir),
+
             // TODO: Add failure checks for the rest as needed.
             _ => true,
         }
@@ -127,6 +214,10 @@ impl World {
                 let player = self.player()?;
                 self.idle(player)
             }
+            Search => {
+                let player = self.player()?;
+                self.entity_search(player)
+            }
             Step(dir) => {
                 let player = self.player()?;
                 self.entity_step(player, *dir)
@@ -135,28 +226,54 @@ impl World {
                 let player = self.player()?;
                 self.entity_melee(player, *dir)
             }
-            Take => {
+            Shoot(dir) => {
                 let player = self.player()?;
-                let item = self.item_at(self.location(player)?)?;
-                self.entity_take(player, item)
+                self.entity_shoot(player, *dir)
+            }
+            Close(dir) => {
+                let player = self.player()?;
+                self.entity_close(player, *dir)
+            }
+            Take(item) => {
+                let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
+                self.entity_take(player, *item)
             }
             Drop(slot) => {
                 let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
                 self.place_entity(self.entity_equipped(player, *slot)?, self.location(player)?);
                 // Dropping items does not cost a turn since you'll be doing it from the inventory
                 // screen.
                 Some(false)
             }
+            DropCount(slot, n) => {
+                let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
+                let item = self.entity_equipped(player, *slot)?;
+                // Split off the dropped part unless the whole stack goes.
+                let dropped = self.split_stack(item, *n).unwrap_or(item);
+                self.place_entity(dropped, self.location(player)?);
+                Some(false)
+            }
             Equip(slot) => {
                 let player = self.player()?;
                 let item = self.entity_equipped(player, *slot)?;
+                // Curses and equipment requirements are checked in can_command.
+                if !self.can_command(cmd) {
+                    return None;
+                }
                 let swap_slot = if slot.is_equipment_slot() {
                     // Remove equipped.
-                    // TODO: Items that can't be removed because of curses etc. trip here.
                     self.free_bag_slot(player)?
                 } else {
                     // Equip from bag.
-                    // TODO: Inability to equip item because stats limits etc. trips here.
                     self.free_equip_slot(player, item)?
                 };
 
@@ -164,7 +281,6 @@ impl World {
                 Some(false)
             }
             InventoryPlace(item, slot) => {
-                // TODO: Check curses, limits etc, as in Equip
                 let player = self.player()?;
 
                 // Checks implemented in can_command, piggyback on those.
@@ -176,7 +292,6 @@ impl World {
                 Some(false)
             }
             InventorySwap(slot1, slot2) => {
-                // TODO: Check curses, limits etc, as in Equip
                 let player = self.player()?;
 
                 // Checks implemented in can_command, piggyback on those.
@@ -201,6 +316,46 @@ impl World {
                 Some(false)
             }
 
+            PutIn(slot, container) => {
+                let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
+                let item = self.entity_equipped(player, *slot)?;
+                let container_slot = self.free_bag_slot(*container)?;
+                self.equip_item(item, *container, container_slot);
+                // Free action like other inventory management.
+                Some(false)
+            }
+            TakeOut(item) => {
+                let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
+                self.entity_take(player, *item)
+            }
+            Buy(item) => {
+                let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
+                self.buy(player, *item)
+            }
+            Sell(slot, shopkeeper) => {
+                let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
+                self.sell(player, self.entity_equipped(player, *slot)?, *shopkeeper)
+            }
+            Steal(item) => {
+                let player = self.player()?;
+                if !self.can_command(cmd) {
+                    return None;
+                }
+                self.steal(player, *item)
+            }
+
             UntargetedAbility { ability, item } => {
                 // XXX: Should these be asserts or just returns?
                 debug_assert!(!ability.is_targeted());
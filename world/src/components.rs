@@ -1,13 +1,18 @@
 use crate::location::Location;
 use crate::location_set::LocationSet;
 use crate::FovStatus;
+use calx_ecs::Entity;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Map field-of-view and remembered terrain.
 #[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct MapMemory {
     pub seen: LocationSet,
     pub remembered: LocationSet,
+    /// Entities sensed without seeing them and the world tick when the knowledge wears off.
+    #[serde(default)]
+    pub detected: BTreeMap<Entity, u64>,
 }
 
 impl MapMemory {
@@ -20,4 +25,9 @@ impl MapMemory {
             None
         }
     }
+
+    /// Return whether the entity's whereabouts are known at the given world tick.
+    pub fn is_detected(&self, e: Entity, tick: u64) -> bool {
+        self.detected.get(&e).is_some_and(|&end| end > tick)
+    }
 }
@@ -39,16 +39,28 @@ pub enum Icon {
     Dog,
     Cat,
     Rat,
+    Shopkeeper,
 
     PlaceholderMob,
     InvisibleMob,
 
     Sword,
+    Bow,
     Helmet,
     Armor,
     Wand1,
     Wand2,
     Scroll1,
+    Ration,
+    Ring1,
+    Scroll2,
+    Book,
+    Shield,
+    Sack,
+    Chest,
+    Gold,
+    Orb,
+    Trap,
 }
 
 /// Entity name and appearance.
@@ -108,22 +120,34 @@ impl Desc {
 
 impl World {
     /// Return visual brush for an entity.
-    pub fn entity_icon(&self, e: Entity) -> Option<Icon> { self.ecs().desc.get(e).map(|x| x.icon) }
+    ///
+    /// Unidentified items show the icon of their disguise.
+    pub fn entity_icon(&self, e: Entity) -> Option<Icon> { self.visible_desc(e).map(|x| x.icon) }
 
     pub fn entity_name(&self, e: Entity) -> String {
-        if let Some(desc) = self.ecs().desc.get(e) {
+        if let Some(desc) = self.visible_desc(e) {
             let count = self.count(e);
+            let prefix = if self.is_known_cursed(e) {
+                "cursed "
+            } else {
+                ""
+            };
 
             if count > 1 {
-                format!("{} {}", count, desc.plural_name())
+                format!("{} {}{}", count, prefix, desc.plural_name())
             } else {
-                desc.singular_name.clone()
+                format!("{}{}", prefix, desc.singular_name)
             }
         } else {
             "N/A".to_string()
         }
     }
 
+    /// Return the description of an entity as the player sees it.
+    fn visible_desc(&self, e: Entity) -> Option<&Desc> {
+        self.unidentified_desc(e).or_else(|| self.ecs().desc.get(e))
+    }
+
     pub fn noun(&self, e: Entity) -> Noun {
         let mut ret = Noun::new(self.entity_name(e));
         if self.is_player(e) {
@@ -0,0 +1,140 @@
+//! Magic mapping and sensing things out of sight
+
+use crate::{sector::Sector, World};
+use calx::Dir6;
+use calx_ecs::Entity;
+
+impl World {
+    /// Return whether the player knows where the entity is without seeing it.
+    pub fn is_detected(&self, e: Entity) -> bool {
+        self.player()
+            .and_then(|p| self.ecs().map_memory.get(p))
+            .is_some_and(|memory| memory.is_detected(e, self.get_tick()))
+    }
+
+    /// Write the layout of the entity's current sector into its map memory.
+    ///
+    /// Only open cells and the walls around them get mapped, solid rock stays unknown. Return
+    /// whether anything new was learned.
+    pub(crate) fn magic_map(&mut self, e: Entity) -> bool {
+        if !self.ecs().map_memory.contains(e) {
+            return false;
+        }
+        let sector = match self.location(e) {
+            Some(loc) => Sector::from(loc),
+            None => return false,
+        };
+
+        let is_open = |loc| !self.terrain(loc).blocks_sight();
+        let mapped: Vec<_> = sector
+            .iter()
+            .filter(|&loc| is_open(loc) || Dir6::iter().any(|&dir| is_open(loc.jump(self, dir))))
+            .collect();
+
+        let memory = &mut self.ecs_mut().map_memory[e];
+        let mut learned = false;
+        for loc in mapped {
+            learned |= !memory.remembered.contains(loc);
+            memory.remembered.insert(loc);
+        }
+        learned
+    }
+
+    /// Let the entity sense everything matching the filter in its current sector for a while.
+    ///
+    /// Return whether anything was detected.
+    pub(crate) fn detect(
+        &mut self,
+        e: Entity,
+        duration: u64,
+        filter: impl Fn(&World, Entity) -> bool,
+    ) -> bool {
+        if !self.ecs().map_memory.contains(e) {
+            return false;
+        }
+        let sector = match self.location(e) {
+            Some(loc) => Sector::from(loc),
+            None => return false,
+        };
+
+        let found: Vec<Entity> = sector
+            .iter()
+            .flat_map(|loc| self.entities_at(loc))
+            .filter(|&x| x != e && filter(self, x))
+            .collect();
+
+        let tick = self.get_tick();
+        let memory = &mut self.ecs_mut().map_memory[e];
+        // Forget about expired detections while at it.
+        memory.detected.retain(|_, &mut end| end > tick);
+        for &x in &found {
+            memory.detected.insert(x, tick + duration);
+        }
+        !found.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        test_util::{give_item, test_world},
+        Ability, Command, ExternalEntity, FovStatus, Sector, World,
+    };
+    use calx::Incremental;
+
+    fn read_scroll(world: &mut World, name: &str, ability: Ability) {
+        let player = world.player().unwrap();
+        let scroll = give_item(world, player, name);
+        world.update(&Command::UntargetedAbility {
+            ability,
+            item: Some(scroll),
+        });
+    }
+
+    #[test]
+    fn test_detection() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+
+        // Find an open spot in the sector that the player hasn't seen.
+        let sector = Sector::from(world.location(player).unwrap());
+        let far = sector
+            .iter()
+            .find(|&loc| world.fov_status(loc).is_none() && world.can_enter(player, loc))
+            .unwrap();
+
+        read_scroll(&mut world, "scroll of magic mapping", Ability::MagicMap);
+        assert_eq!(world.fov_status(far), Some(FovStatus::Remembered));
+
+        // Mapping shows the walls around open space, but not the solid rock beyond.
+        assert!(sector.iter().any(|loc| world.fov_status(loc).is_none()));
+
+        let rat = world.spawn(&ExternalEntity::from_name("rat").unwrap(), far);
+        let sword = world.spawn(&ExternalEntity::from_name("sword").unwrap(), far);
+        assert!(!world.is_detected(rat));
+
+        read_scroll(
+            &mut world,
+            "scroll of detect monsters",
+            Ability::DetectMonsters,
+        );
+        let detected_at = world.get_tick();
+        assert!(world.is_detected(rat));
+        assert!(!world.is_detected(sword));
+        assert!(!world.is_detected(player));
+
+        read_scroll(&mut world, "scroll of detect items", Ability::DetectItems);
+        assert!(world.is_detected(sword));
+
+        // Sensing monsters wears off sooner than sensing items.
+        while world.get_tick() <= detected_at + 240 {
+            if world.player_can_act() {
+                world.update(&Command::Pass);
+            } else {
+                world.update(&Command::Wait);
+            }
+        }
+        assert!(!world.is_detected(rat));
+        assert!(world.is_detected(sword));
+    }
+}
@@ -7,13 +7,33 @@ pub enum Effect {
     Hit { amount: u32, damage: Damage },
     /// Cause erratic behavior for a time.
     Confuse,
+    /// Drain an amount of experience, possibly costing levels.
+    ///
+    /// If lethal, a target that runs out of experience to lose dies.
+    Drain { amount: u32, lethal: bool },
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Damage {
+    /// Weapons, claws and falling rocks. Soaked by armor.
     Physical,
     Fire,
     Electricity,
+    /// Life force draining.
+    Drain,
+    Poison,
+}
+
+impl Damage {
+    pub fn name(self) -> &'static str {
+        match self {
+            Damage::Physical => "blow",
+            Damage::Fire => "fire",
+            Damage::Electricity => "electricity",
+            Damage::Drain => "drain",
+            Damage::Poison => "poison",
+        }
+    }
 }
 
 /// Actions a being can do
@@ -21,18 +41,43 @@ pub enum Damage {
 pub enum Ability {
     // --- Untargeted ---
     LightningBolt,
-    // MagicMap
+    /// Eat the food item that grants the ability.
+    Eat,
+    /// Learn the ability taught by the spellbook that grants this ability.
+    Study,
+    /// Turn adjacent animals into allies.
+    Tame,
+    /// Lift curses from everything the user carries.
+    RemoveCurse,
+    /// Refill the charges of rechargeable items the user carries.
+    Recharge,
+    /// Reveal the layout of the user's surroundings.
+    MagicMap,
+    /// Sense the creatures in the user's surroundings for a while.
+    DetectMonsters,
+    /// Sense the items in the user's surroundings for a while.
+    DetectItems,
+    /// Heal the user's wounds.
+    Heal,
+    /// Make the user heal quickly for a while.
+    Regenerate,
+    /// Make the user unseen for a while.
+    Invisibility,
 
     // --- Targeted ---
     Fireball,
     Confuse,
 }
 
+/// Innate abilities of an entity.
+pub type Abilities = Vec<Ability>;
+
 impl Ability {
     pub fn is_targeted(self) -> bool {
         use Ability::*;
         match self {
-            LightningBolt => false,
+            LightningBolt | Eat | Study | Tame | RemoveCurse | Recharge | MagicMap | DetectMonsters
+            | DetectItems | Heal | Regenerate | Invisibility => false,
             _ => true,
         }
     }
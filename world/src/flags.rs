@@ -2,7 +2,13 @@ use crate::location::Location;
 use calx_ecs::Entity;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Default)]
+/// Default sector radius around the player where mobs are updated.
+///
+/// Covers the sectors adjacent to the player's sector, so mobs chasing the player over a sector
+/// edge keep up.
+pub const DEFAULT_ACTIVE_RADIUS: i32 = 1;
+
+#[derive(Serialize, Deserialize)]
 pub struct Flags {
     pub camera: Location,
     pub tick: u64,
@@ -11,4 +17,23 @@ pub struct Flags {
     /// Store the player entity here for fast access.
     pub player: Option<Entity>,
     pub depth: i32,
+    /// Sector radius around the player for the active mob set.
+    #[serde(default = "default_active_radius")]
+    pub active_radius: i32,
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags {
+            camera: Default::default(),
+            tick: 0,
+            anim_tick: 0,
+            player_acted: false,
+            player: None,
+            depth: 0,
+            active_radius: DEFAULT_ACTIVE_RADIUS,
+        }
+    }
 }
+
+pub(crate) fn default_active_radius() -> i32 { DEFAULT_ACTIVE_RADIUS }
@@ -0,0 +1,252 @@
+//! Nutrition, hunger and eating
+
+use crate::{
+    grammar::{GrammarPart, Noun},
+    msg,
+    stats::{Stats, Status},
+    ActionOutcome, ItemType, World,
+};
+use calx::RngExt;
+use calx_ecs::Entity;
+use serde::{Deserialize, Serialize};
+
+/// Nutrition a new creature starts with.
+const STARTING_NUTRITION: i32 = 900;
+/// Eating can't fill you up beyond this.
+const MAX_NUTRITION: i32 = 2000;
+/// Nutrition level below which you starve to death.
+const STARVATION_LIMIT: i32 = -200;
+/// Chance of fainting per action when fainting from hunger.
+const FAINT_CHANCE_ONE_IN: u32 = 10;
+/// How long a faint lasts.
+const FAINT_DURATION: u32 = 60;
+/// Attack and defense lost when weak from hunger.
+const WEAK_PENALTY: i32 = 2;
+
+/// Nutrition reserve component. Only entities that have one need to eat.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Satiation {
+    /// Nutrition left, goes down by one with every action the entity takes.
+    pub nutrition: i32,
+}
+
+impl Default for Satiation {
+    fn default() -> Self {
+        Satiation {
+            nutrition: STARTING_NUTRITION,
+        }
+    }
+}
+
+/// How hungry a creature is, derived from its nutrition level.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum Hunger {
+    Satiated,
+    NotHungry,
+    Hungry,
+    /// Attack and defense are reduced and there is no natural regeneration.
+    Weak,
+    /// Like weak, but the creature may also pass out for a while at any moment.
+    Fainting,
+}
+
+impl Hunger {
+    fn from_nutrition(nutrition: i32) -> Hunger {
+        use Hunger::*;
+        match nutrition {
+            n if n > 1500 => Satiated,
+            n if n > 300 => NotHungry,
+            n if n > 100 => Hungry,
+            n if n > 0 => Weak,
+            _ => Fainting,
+        }
+    }
+
+    /// Stat modifier caused by the hunger state.
+    pub(crate) fn stat_penalty(self) -> Stats {
+        if self >= Hunger::Weak {
+            Stats::default()
+                .attack(-WEAK_PENALTY)
+                .defense(-WEAK_PENALTY)
+        } else {
+            Stats::default()
+        }
+    }
+}
+
+impl World {
+    /// Return how hungry an entity is.
+    ///
+    /// Entities that don't need to eat are never hungry.
+    pub fn hunger(&self, e: Entity) -> Hunger {
+        self.ecs()
+            .satiation
+            .get(e)
+            .map_or(Hunger::NotHungry, |s| Hunger::from_nutrition(s.nutrition))
+    }
+
+    /// Return whether the entity is fed well enough to heal naturally.
+    pub fn can_regenerate(&self, e: Entity) -> bool { self.hunger(e) < Hunger::Weak }
+
+    /// Consume one unit of nutrition after the entity has taken an action.
+    ///
+    /// Does nothing for entities that don't need to eat.
+    pub(crate) fn consume_nutrition(&mut self, e: Entity) {
+        let nutrition = match self.ecs().satiation.get(e) {
+            Some(s) => s.nutrition - 1,
+            None => return,
+        };
+
+        if nutrition <= STARVATION_LIMIT {
+            msg!("[One] collapse[s] from starvation."; self.subject(e));
+            // Kill outright, armor shouldn't save you from starving.
+            self.kill_entity(e);
+            return;
+        }
+
+        self.set_nutrition(e, nutrition);
+
+        if self.hunger(e) == Hunger::Fainting && self.rng().one_chance_in(FAINT_CHANCE_ONE_IN) {
+            msg!("[One] faint[s] from hunger."; self.subject(e));
+            self.gain_status(e, Status::Delayed, FAINT_DURATION);
+        }
+    }
+
+    /// Eat a food item, destroying one of it.
+    pub(crate) fn eat(&mut self, e: Entity, item: Entity) -> ActionOutcome {
+        let amount = match self.item_type(item) {
+            Some(ItemType::Food(amount)) => amount as i32,
+            _ => return None,
+        };
+        let nutrition = self.ecs().satiation.get(e)?.nutrition;
+
+        // Name a single item, not the whole stack.
+        let name = self.ecs().desc.get(item)?.singular_name.clone();
+        msg!("[One] eat[s] [a thing]."; self.subject(e), GrammarPart::Object(Noun::new(name)));
+
+        self.set_nutrition(e, (nutrition + amount).min(MAX_NUTRITION));
+        Some(true)
+    }
+
+    /// Change the nutrition level and react to changes in hunger.
+    fn set_nutrition(&mut self, e: Entity, nutrition: i32) {
+        let old_hunger = self.hunger(e);
+        if let Some(s) = self.ecs_mut().satiation.get_mut(e) {
+            s.nutrition = nutrition;
+        }
+        let hunger = self.hunger(e);
+        if hunger == old_hunger {
+            return;
+        }
+
+        self.rebuild_stats(e);
+
+        use Hunger::*;
+        if hunger > old_hunger {
+            match hunger {
+                Hungry => {
+                    msg!("[One] [is] getting hungry."; self.subject(e));
+                }
+                Weak => {
+                    msg!("[One] [is] weak with hunger."; self.subject(e));
+                }
+                Fainting => {
+                    msg!("[One] [is] fainting from lack of food."; self.subject(e));
+                }
+                _ => {}
+            }
+        } else {
+            match hunger {
+                Satiated => {
+                    msg!("[One] [is] full."; self.subject(e));
+                }
+                NotHungry => {
+                    msg!("[One] [is] no longer hungry."; self.subject(e));
+                }
+                _ => {
+                    msg!("[One] feel[s] less hungry."; self.subject(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Hunger, MAX_NUTRITION, STARVATION_LIMIT, WEAK_PENALTY};
+    use crate::{
+        test_util::{give_item, test_world},
+        Ability, Command, Slot,
+    };
+    use calx::Incremental;
+
+    #[test]
+    fn test_hunger() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let attack = world.stats(player).base_attack;
+        let defense = world.stats(player).base_defense;
+
+        // Every action burns nutrition.
+        let nutrition = world.ecs().satiation[player].nutrition;
+        world.consume_nutrition(player);
+        assert_eq!(world.ecs().satiation[player].nutrition, nutrition - 1);
+        assert_eq!(world.hunger(player), Hunger::NotHungry);
+
+        world.ecs_mut().satiation[player].nutrition = 301;
+        world.consume_nutrition(player);
+        assert_eq!(world.hunger(player), Hunger::Hungry);
+        assert!(world.can_regenerate(player));
+        assert_eq!(world.stats(player).base_attack, attack);
+
+        // Weakness from hunger hurts stats and stops healing.
+        world.ecs_mut().satiation[player].nutrition = 101;
+        world.consume_nutrition(player);
+        assert_eq!(world.hunger(player), Hunger::Weak);
+        assert!(!world.can_regenerate(player));
+        assert_eq!(world.stats(player).base_attack, attack - WEAK_PENALTY);
+        assert_eq!(world.stats(player).base_defense, defense - WEAK_PENALTY);
+
+        world.ecs_mut().satiation[player].nutrition = 1;
+        world.consume_nutrition(player);
+        assert_eq!(world.hunger(player), Hunger::Fainting);
+        assert!(world.is_alive(player));
+
+        // Eating fixes things and uses up the food.
+        let ration = give_item(&mut world, player, "ration");
+        world.update(&Command::UntargetedAbility {
+            ability: Ability::Eat,
+            item: Some(ration),
+        });
+        assert!(!world.is_alive(ration));
+        // The meal is an action, so it costs a unit of nutrition itself.
+        assert_eq!(world.ecs().satiation[player].nutrition, 800 - 1);
+        assert_eq!(world.hunger(player), Hunger::NotHungry);
+        assert!(world.can_regenerate(player));
+        assert_eq!(world.stats(player).base_attack, attack);
+        assert_eq!(world.stats(player).base_defense, defense);
+
+        // You can only eat so much.
+        for _ in 0..3 {
+            let ration = give_item(&mut world, player, "ration");
+            world.eat(player, ration);
+        }
+        assert_eq!(world.ecs().satiation[player].nutrition, MAX_NUTRITION);
+        assert_eq!(world.hunger(player), Hunger::Satiated);
+    }
+
+    #[test]
+    fn test_starvation() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+
+        // Armor doesn't help against starving.
+        let armor = give_item(&mut world, player, "armor");
+        world.equip_item(armor, player, Slot::Body);
+        assert!(world.stats(player).armor > 0);
+
+        world.ecs_mut().satiation[player].nutrition = STARVATION_LIMIT + 1;
+        world.consume_nutrition(player);
+        assert!(!world.is_alive(player));
+    }
+}
@@ -0,0 +1,137 @@
+//! Unidentified magic items and their randomized appearances
+
+use crate::{
+    desc::{Desc, Icon},
+    grammar::{GrammarPart, Noun},
+    msg, spec, World,
+};
+use calx::seeded_rng;
+use calx_ecs::Entity;
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+
+const SCROLL_APPEARANCES: [&str; 12] = [
+    "crimson", "azure", "ochre", "ivory", "ashen", "violet", "verdant", "golden", "sable",
+    "saffron", "russet", "pearly",
+];
+const SCROLL_ICONS: [Icon; 2] = [Icon::Scroll1, Icon::Scroll2];
+
+const WAND_APPEARANCES: [&str; 12] = [
+    "oak", "bone", "iron", "glass", "ebony", "copper", "jade", "silver", "brass", "crystal",
+    "willow", "obsidian",
+];
+const WAND_ICONS: [Icon; 2] = [Icon::Wand1, Icon::Wand2];
+
+/// Kinds of items that look alike until identified.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+enum MagicClass {
+    Scroll,
+    Wand,
+}
+
+impl MagicClass {
+    fn from_icon(icon: Icon) -> Option<MagicClass> {
+        if SCROLL_ICONS.contains(&icon) {
+            Some(MagicClass::Scroll)
+        } else if WAND_ICONS.contains(&icon) {
+            Some(MagicClass::Wand)
+        } else {
+            None
+        }
+    }
+
+    fn appearances(self) -> (&'static [&'static str], &'static [Icon], &'static str) {
+        match self {
+            MagicClass::Scroll => (&SCROLL_APPEARANCES, &SCROLL_ICONS, "scroll"),
+            MagicClass::Wand => (&WAND_APPEARANCES, &WAND_ICONS, "wand"),
+        }
+    }
+}
+
+/// Assign shuffled appearances to every magic item kind in the spec database.
+///
+/// The result only depends on the seed and the spec data, so replays get the same appearances.
+/// If a class has more kinds than there are appearances, the extra kinds are left without one and
+/// are always known.
+pub(crate) fn shuffle_appearances(seed: u32) -> BTreeMap<String, Desc> {
+    let mut kinds: BTreeMap<MagicClass, Vec<String>> = BTreeMap::new();
+    for spec in spec::iter_specs() {
+        if let Some(class) = MagicClass::from_icon(spec.icon()) {
+            kinds.entry(class).or_default().push(spec.id().to_string());
+        }
+    }
+
+    let mut ret = BTreeMap::new();
+    for (class, kinds) in kinds {
+        let (names, icons, noun) = class.appearances();
+        let mut rng = seeded_rng(&(seed, noun));
+        let mut names = names.to_vec();
+        names.shuffle(&mut rng);
+        let mut icons = icons.to_vec();
+        icons.shuffle(&mut rng);
+
+        for (i, (kind, name)) in kinds.into_iter().zip(names).enumerate() {
+            let desc = Desc::new(&format!("{} {}", name, noun), icons[i % icons.len()]);
+            ret.insert(kind, desc);
+        }
+    }
+    ret
+}
+
+impl World {
+    /// Return whether the player knows what the item is.
+    pub fn is_identified(&self, e: Entity) -> bool { self.unidentified_desc(e).is_none() }
+
+    /// Return the disguise description of an item if the player hasn't identified it yet.
+    pub(crate) fn unidentified_desc(&self, e: Entity) -> Option<&Desc> {
+        let kind = &self.ecs().desc.get(e)?.singular_name;
+        if self.identified_kinds.contains(kind) {
+            return None;
+        }
+        self.item_appearances.get(kind)
+    }
+
+    /// Identify the kind of an item the player has used.
+    pub(crate) fn identify_by_use(&mut self, user: Entity, item: Entity) {
+        if !self.is_player(user) || self.is_identified(item) {
+            return;
+        }
+        let kind = self.ecs().desc[item].singular_name.clone();
+        self.identified_kinds.insert(kind.clone());
+        msg!("[One] identif[ies] [a thing]."; self.subject(user), GrammarPart::Object(Noun::new(kind)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_util::test_world, ExternalEntity};
+
+    #[test]
+    fn test_identification() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+
+        let scroll = world.spawn(
+            &ExternalEntity::from_name("scroll of lightning").unwrap(),
+            loc,
+        );
+        assert!(!world.is_identified(scroll));
+        let name = world.entity_name(scroll);
+        assert!(name.ends_with(" scroll"));
+        assert_ne!(name, "scroll of lightning");
+
+        // Other scrolls get different appearances.
+        let other = world.spawn(&ExternalEntity::from_name("scroll of taming").unwrap(), loc);
+        assert_ne!(world.entity_name(other), name);
+
+        world.identify_by_use(player, scroll);
+        assert!(world.is_identified(scroll));
+        assert_eq!(world.entity_name(scroll), "scroll of lightning");
+        assert!(!world.is_identified(other));
+
+        // Mundane items are always known.
+        let sword = world.spawn(&ExternalEntity::from_name("sword").unwrap(), loc);
+        assert!(world.is_identified(sword));
+    }
+}
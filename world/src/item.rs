@@ -1,6 +1,6 @@
 //! Item and equipment logic
 
-use crate::{msg, Ability, ActionOutcome, Location, World};
+use crate::{msg, spatial::Place, Ability, ActionOutcome, Location, World};
 use calx::{hex_neighbors, CellVector, HexGeom};
 use calx_ecs::Entity;
 use euclid::vec2;
@@ -37,7 +37,7 @@ impl Slot {
         use self::Slot::*;
         match self {
             RightHand => equip_type == Some(EquipType::Melee),
-            LeftHand => false, // TODO: Shields etc
+            LeftHand => equip_type == Some(EquipType::Shield),
             Ranged => equip_type == Some(EquipType::Ranged),
             Head => equip_type == Some(EquipType::Head),
             Body => equip_type == Some(EquipType::Body),
@@ -64,15 +64,46 @@ pub enum ItemType {
     Helmet,
     Armor,
     Boots,
+    /// Worn in the off hand
+    Shield,
     /// Passive effects when equipped
     Trinket,
     /// Trigges an untargeted ability.
-    ///
-    /// By convention these items are spent after single use.
     UntargetedUsable(Ability),
     TargetedUsable(Ability),
     /// Consumed instantly when stepped on.
     Instant(Ability),
+    /// Can be eaten for the given amount of nutrition.
+    Food(u32),
+    /// Teaches the reader an innate ability.
+    Spellbook(Ability),
+    /// Carried around to hold other items.
+    Container,
+    /// Too big to pick up, like chests.
+    Furniture,
+    /// Money for trading with shopkeepers.
+    Currency,
+}
+
+/// How using an item spends it.
+#[derive(Copy, Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum Consumption {
+    /// Used up on the first use, like scrolls and food.
+    SingleUse,
+    /// Has a number of charges and crumbles away once they're spent.
+    Charged(u32),
+    /// Has a number of charges and stays around when empty so it can be recharged.
+    Rechargeable(u32),
+}
+
+impl Consumption {
+    /// Number of uses a fresh item has.
+    pub fn max_charges(self) -> u32 {
+        match self {
+            Consumption::SingleUse => 1,
+            Consumption::Charged(n) | Consumption::Rechargeable(n) => n,
+        }
+    }
 }
 
 #[derive(Copy, Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
@@ -82,6 +113,7 @@ pub enum EquipType {
     Head,
     Body,
     Feet,
+    Shield,
     Trinket,
 }
 
@@ -91,6 +123,30 @@ pub struct Item {
     pub item_type: ItemType,
     /// How many uses a wand or similar has left.
     pub charges: u32,
+    /// How using the item spends it.
+    pub consumption: Consumption,
+    /// Cursed items can't be taken off once equipped.
+    pub cursed: bool,
+    /// Whether the player has found out the item is cursed.
+    pub curse_known: bool,
+    /// Character level needed to equip the item.
+    pub min_level: i32,
+    /// Power needed to equip the item.
+    pub min_power: i32,
+    /// Value of a single item in gold.
+    pub price: u32,
+}
+
+/// An entity that holds other items.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Container {
+    /// Number of bag slots in the container.
+    pub capacity: u32,
+    /// Number of random items the container is filled with when generated in the world.
+    pub loot: u32,
+    /// Gold pieces the container is filled with when generated in the world.
+    #[serde(default)]
+    pub gold: u32,
 }
 
 /// An entity that can become a stack of multiple copies.
@@ -119,7 +175,7 @@ impl World {
             return None;
         }
 
-        if !self.is_item(item) {
+        if !self.is_portable(item) {
             return None;
         }
 
@@ -128,6 +184,24 @@ impl World {
             panic!("Trying to pick up an entity you are inside of. This shouldn't happen");
         }
 
+        // Grab the name first, the item may vanish into a stack.
+        let name = self.object(item);
+        if !self.stash_item(e, item) {
+            // No more inventory space
+            return None;
+        }
+
+        if self.is_player(e) {
+            msg!("[One] pick[s] up [a thing]."; self.subject(e), name);
+        }
+        self.end_turn(e);
+        Some(true)
+    }
+
+    /// Put an item in the entity's bag, merging it into existing stacks when possible.
+    ///
+    /// Return false if there was no room for the item.
+    pub(crate) fn stash_item(&mut self, e: Entity, item: Entity) -> bool {
         // Item might go into a stack, look for stacks.
         if self.is_stackable(item) {
             let bag = self.entities_in_bag(e);
@@ -142,11 +216,12 @@ impl World {
                     let max_size = self.max_stack_size(*e);
 
                     if stack_size <= max_size {
-                        // Merge into an existing stack, delete incoming item.
+                        // Merge into an existing stack, delete incoming item. The whole stack
+                        // goes, so don't use kill_entity that only takes one off the top.
                         self.ecs_mut().stacking[*e].count = stack_size;
-                        self.kill_entity(item);
+                        self.spatial.remove(item);
                         // Item was consumed, so we're done here.
-                        return Some(true);
+                        return true;
                     } else {
                         // Top up the stack, our item remains so we keep looking for a place or
                         // more items to merge it with.
@@ -160,16 +235,9 @@ impl World {
 
         if let Some(slot) = self.free_bag_slot(e) {
             self.equip_item(item, e, slot);
-            if self.is_player(e) {
-                msg!("[One] pick[s] up [a thing].";
-                    self.subject(e), self.object(item));
-            }
-
-            self.end_turn(e);
-            Some(true)
+            true
         } else {
-            // No more inventory space
-            None
+            false
         }
     }
 
@@ -202,6 +270,23 @@ impl World {
 
     pub fn is_stackable(&self, e: Entity) -> bool { self.ecs().stacking.contains(e) }
 
+    /// Split a stack in two, taking `count` items into a new stack.
+    ///
+    /// The new stack is a copy of the old one except for the count, so the two will stack back
+    /// together. The new entity isn't placed anywhere, the caller must do that. Return `None` if
+    /// the entity isn't a stack with more than `count` items.
+    pub(crate) fn split_stack(&mut self, e: Entity, count: u32) -> Option<Entity> {
+        if !self.is_stackable(e) || count == 0 || count >= self.count(e) {
+            return None;
+        }
+
+        let mut split = self.extract(e)?;
+        split.loadout.stacking = Some(Stacking { count });
+        let ret = self.inject(&split);
+        self.ecs_mut().stacking[e].count -= count;
+        Some(ret)
+    }
+
     /// Return count on entity if it's a stack
     pub fn count(&self, e: Entity) -> u32 {
         if let Some(stacking) = self.ecs().stacking.get(e) {
@@ -213,7 +298,10 @@ impl World {
     }
 
     pub fn max_stack_size(&self, e: Entity) -> u32 {
-        if self.ecs().stacking.contains(e) {
+        if self.item_type(e) == Some(ItemType::Currency) {
+            // Don't make the player juggle dozens of gold stacks.
+            99_999
+        } else if self.ecs().stacking.contains(e) {
             99
         } else {
             1
@@ -262,6 +350,14 @@ impl World {
         self.entities_at(loc).into_iter().find(|&e| self.is_item(e))
     }
 
+    /// Return all items at given location.
+    pub fn items_at(&self, loc: Location) -> Vec<Entity> {
+        self.entities_at(loc)
+            .into_iter()
+            .filter(|&e| self.is_item(e))
+            .collect()
+    }
+
     pub fn can_drop_item_at(&self, loc: Location) -> bool {
         if !self.is_valid_location(loc) {
             return false;
@@ -280,11 +376,54 @@ impl World {
     }
 
     pub fn free_bag_slot(&self, e: Entity) -> Option<Slot> {
-        (0..BAG_CAPACITY)
+        (0..self.bag_capacity(e))
             .find(|&i| self.entity_equipped(e, Slot::Bag(i)).is_none())
             .map(Slot::Bag)
     }
 
+    /// Return number of bag slots the entity has.
+    ///
+    /// Containers have their own capacity, everything else gets the full bag.
+    pub fn bag_capacity(&self, e: Entity) -> u32 {
+        self.ecs()
+            .container
+            .get(e)
+            .map_or(BAG_CAPACITY, |c| c.capacity)
+    }
+
+    pub fn is_container(&self, e: Entity) -> bool { self.ecs().container.contains(e) }
+
+    /// Return whether the item can be picked up.
+    pub fn is_portable(&self, e: Entity) -> bool {
+        matches!(self.item_type(e), Some(t) if t != ItemType::Furniture)
+    }
+
+    /// Return whether entity can get at the contents of a container.
+    ///
+    /// Containers can be used when they're on the floor under the entity or in its bag.
+    pub fn can_reach_container(&self, e: Entity, container: Entity) -> bool {
+        if !self.is_container(container) {
+            return false;
+        }
+        match self.spatial.get(container) {
+            Some(Place::At(loc)) => self.location(e) == Some(loc),
+            Some(Place::In(parent, slot)) => parent == e && !slot.is_equipment_slot(),
+            None => false,
+        }
+    }
+
+    /// Return the containers entity can reach, the ones on the floor first.
+    pub fn reachable_containers(&self, e: Entity) -> Vec<Entity> {
+        let floor = self
+            .location(e)
+            .map_or_else(Vec::new, |loc| self.items_at(loc));
+        floor
+            .into_iter()
+            .chain(self.entities_in_bag(e).into_iter().map(|(_, item)| item))
+            .filter(|&item| self.is_container(item))
+            .collect()
+    }
+
     pub fn free_equip_slot(&self, e: Entity, item: Entity) -> Option<Slot> {
         Slot::equipment_iter()
             .find(|&&x| x.accepts(self.equip_type(item)) && self.entity_equipped(e, x).is_none())
@@ -296,18 +435,21 @@ impl World {
         self.ecs().item.get(item).map_or(0, |i| i.charges)
     }
 
+    /// Return how using the item spends it.
+    pub fn consumption(&self, item: Entity) -> Option<Consumption> {
+        self.ecs().item.get(item).map(|i| i.consumption)
+    }
+
+    /// Return whether the item has charges that should be shown to the player.
+    pub fn has_charges(&self, item: Entity) -> bool {
+        matches!(
+            self.consumption(item),
+            Some(Consumption::Charged(_)) | Some(Consumption::Rechargeable(_))
+        )
+    }
+
     pub fn destroy_after_use(&self, item: Entity) -> bool {
-        // XXX: Fragile. What we want here is to tag potions and scrolls as destroyed when used and
-        // wands to stick around. Current item data doesn't have is_potion or is_scroll, but
-        // coincidentally the scrolls tend to be untargeted and the wands tend to be targeted
-        // spells, so we'll just use that as proxy.
-        self.ecs().item.get(item).map_or(false, |i| {
-            if let ItemType::UntargetedUsable(_) = i.item_type {
-                true
-            } else {
-                false
-            }
-        })
+        self.consumption(item) == Some(Consumption::SingleUse)
     }
 
     pub fn equip_type(&self, item: Entity) -> Option<EquipType> {
@@ -318,20 +460,265 @@ impl World {
             Some(Helmet) => Some(EquipType::Head),
             Some(Armor) => Some(EquipType::Body),
             Some(Boots) => Some(EquipType::Feet),
+            Some(Shield) => Some(EquipType::Shield),
             Some(Trinket) => Some(EquipType::Trinket),
             _ => None,
         }
     }
 
-    pub(crate) fn drain_charge(&mut self, item: Entity) {
-        if self.destroy_after_use(item) {
-            self.kill_entity(item);
+    /// Return whether the item is cursed.
+    pub fn is_cursed(&self, item: Entity) -> bool {
+        matches!(self.ecs().item.get(item), Some(i) if i.cursed)
+    }
+
+    /// Return whether the item is cursed and the player knows about it.
+    pub fn is_known_cursed(&self, item: Entity) -> bool {
+        matches!(self.ecs().item.get(item), Some(i) if i.cursed && i.curse_known)
+    }
+
+    /// Return whether an item can be moved out of the slot it's in.
+    ///
+    /// Cursed items stick to their equipment slot.
+    pub fn can_unequip(&self, item: Entity) -> bool {
+        !(self.is_cursed(item)
+            && matches!(self.entity_slot(item), Some(slot) if slot.is_equipment_slot()))
+    }
+
+    /// Return whether the entity is experienced and powerful enough to equip the item.
+    pub fn meets_requirements(&self, e: Entity, item: Entity) -> bool {
+        if let Some(i) = self.ecs().item.get(item) {
+            self.stats(e).level >= i.min_level && self.power(e) >= i.min_power
+        } else {
+            true
+        }
+    }
+
+    /// Return whether an item in the entity's inventory can be moved into a slot.
+    ///
+    /// Checks curses and equipment requirements, not whether the slot accepts the item.
+    pub fn can_move_item(&self, e: Entity, item: Entity, slot: Slot) -> bool {
+        if self.entity_slot(item) != Some(slot) && !self.can_unequip(item) {
+            return false;
+        }
+        !slot.is_equipment_slot() || self.meets_requirements(e, item)
+    }
+
+    /// Mark an item's curse as known to the player.
+    ///
+    /// Return whether the curse was news.
+    pub(crate) fn reveal_curse(&mut self, item: Entity) -> bool {
+        match self.ecs_mut().item.get_mut(item) {
+            Some(i) if i.cursed && !i.curse_known => {
+                i.curse_known = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Lift the curse from an item.
+    ///
+    /// Return whether the item was cursed.
+    pub(crate) fn uncurse(&mut self, item: Entity) -> bool {
+        match self.ecs_mut().item.get_mut(item) {
+            Some(i) if i.cursed => {
+                i.cursed = false;
+                true
+            }
+            _ => false,
         }
+    }
 
-        if let Some(i) = self.ecs_mut().item.get_mut(item) {
-            if i.charges > 0 {
-                i.charges -= 1;
+    /// Spend one use of an item used by entity.
+    pub(crate) fn drain_charge(&mut self, e: Entity, item: Entity) {
+        match self.consumption(item) {
+            Some(Consumption::SingleUse) => self.kill_entity(item),
+            Some(consumption) => {
+                if let Some(i) = self.ecs_mut().item.get_mut(item) {
+                    i.charges = i.charges.saturating_sub(1);
+                }
+                if self.uses_left(item) > 0 {
+                    return;
+                }
+
+                if let Consumption::Charged(_) = consumption {
+                    if self.is_player(e) {
+                        msg!("[One] crumble[s] to dust."; self.subject(item));
+                    }
+                    self.kill_entity(item);
+                } else if self.is_player(e) {
+                    msg!("[Another] is now empty."; self.subject(e), self.object(item));
+                }
             }
+            None => {}
+        }
+    }
+
+    /// Refill the charges of a rechargeable item.
+    ///
+    /// Return whether the item gained any charges.
+    pub(crate) fn recharge(&mut self, item: Entity) -> bool {
+        match self.ecs_mut().item.get_mut(item) {
+            Some(i) => match i.consumption {
+                Consumption::Rechargeable(n) if i.charges < n => {
+                    i.charges = n;
+                    true
+                }
+                _ => false,
+            },
+            None => false,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::EquipType;
+    use crate::{
+        test_util::{give_item, test_world, wait_for_turn},
+        Command, ExternalEntity, Slot,
+    };
+    use calx::Incremental;
+
+    #[test]
+    fn test_equipment_restrictions() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+
+        // Cursed helmet can be put on, but not taken off.
+        let helmet = give_item(&mut world, player, "helmet");
+        world.ecs_mut().item[helmet].cursed = true;
+        assert!(!world.is_known_cursed(helmet));
+        assert!(world.can_command(&Command::InventoryPlace(helmet, Slot::Head)));
+
+        world.equip_item(helmet, player, Slot::Head);
+        assert!(world.is_known_cursed(helmet));
+        assert_eq!(world.entity_name(helmet), "cursed helmet");
+        assert!(!world.can_command(&Command::Equip(Slot::Head)));
+        assert!(!world.can_command(&Command::Drop(Slot::Head)));
+        assert!(!world.can_command(&Command::InventoryPlace(helmet, Slot::Bag(10))));
+
+        world.uncurse(helmet);
+        assert!(world.can_command(&Command::Equip(Slot::Head)));
+        assert!(world.can_command(&Command::Drop(Slot::Head)));
+
+        // Starting character isn't experienced enough for plate armor.
+        let armor = give_item(&mut world, player, "plate armor");
+        let bag = world.entity_slot(armor).unwrap();
+        assert!(!world.can_command(&Command::Equip(bag)));
+        assert!(!world.can_command(&Command::InventoryPlace(armor, Slot::Body)));
+        assert!(world.can_command(&Command::InventoryPlace(armor, Slot::Bag(10))));
+    }
+
+    #[test]
+    fn test_off_hand() {
+        assert!(Slot::LeftHand.accepts(Some(EquipType::Shield)));
+        assert!(!Slot::LeftHand.accepts(Some(EquipType::Melee)));
+        assert!(!Slot::RightHand.accepts(Some(EquipType::Shield)));
+    }
+
+    #[test]
+    fn test_charges() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+
+        let wand = world.spawn(&ExternalEntity::from_name("wand of fireball").unwrap(), loc);
+        assert!(world.has_charges(wand));
+        assert_eq!(world.uses_left(wand), 5);
+        for _ in 0..5 {
+            world.drain_charge(player, wand);
+        }
+        // Rechargeable wands stick around when empty.
+        assert!(world.is_alive(wand));
+        assert_eq!(world.uses_left(wand), 0);
+        assert!(world.recharge(wand));
+        assert_eq!(world.uses_left(wand), 5);
+
+        let wand = world.spawn(
+            &ExternalEntity::from_name("wand of confusion").unwrap(),
+            loc,
+        );
+        for _ in 0..8 {
+            world.drain_charge(player, wand);
+        }
+        assert!(!world.is_alive(wand));
+
+        let scroll = world.spawn(
+            &ExternalEntity::from_name("scroll of lightning").unwrap(),
+            loc,
+        );
+        assert!(!world.has_charges(scroll));
+        world.drain_charge(player, scroll);
+        assert!(!world.is_alive(scroll));
+    }
+
+    #[test]
+    fn test_split_stack() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+
+        let rations = give_item(&mut world, player, "ration");
+        world.ecs_mut().stacking[rations].count = 5;
+        let slot = world.entity_slot(rations).unwrap();
+
+        assert!(!world.can_command(&Command::DropCount(slot, 6)));
+        assert!(world.can_command(&Command::DropCount(slot, 2)));
+        world.update(&Command::DropCount(slot, 2));
+        assert_eq!(world.count(rations), 3);
+
+        let dropped = world.items_at(loc)[0];
+        assert_eq!(world.count(dropped), 2);
+        assert!(world.can_stack_with(rations, dropped));
+
+        // Picking up merges the stacks back together.
+        world.update(&Command::Take(dropped));
+        assert_eq!(world.count(rations), 5);
+        assert!(!world.is_alive(dropped));
+    }
+
+    #[test]
+    fn test_containers() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+        wait_for_turn(&mut world);
+
+        // Generated chests come with loot.
+        let mut loaded = ExternalEntity::from_name("chest").unwrap();
+        world.fill_container(&mut loaded, loc, &mut calx::seeded_rng(&1));
+        assert_eq!(loaded.contents.len(), 3);
+
+        // Chests can't be picked up but can be reached from the floor.
+        let chest = world.spawn(&ExternalEntity::from_name("chest").unwrap(), loc);
+        assert!(!world.can_command(&Command::Take(chest)));
+        assert!(world.can_reach_container(player, chest));
+        assert_eq!(world.reachable_containers(player), vec![chest]);
+
+        let sword = give_item(&mut world, player, "sword");
+        let slot = world.entity_slot(sword).unwrap();
+
+        world.update(&Command::PutIn(slot, chest));
+        assert_eq!(world.entity_parent(sword), Some(chest));
+        assert_eq!(world.location(sword), Some(loc));
+        assert!(world.entities_in_bag(player).is_empty());
+
+        // Containers don't nest.
+        let sack = give_item(&mut world, player, "sack");
+        let sack_slot = world.entity_slot(sack).unwrap();
+        assert!(!world.can_command(&Command::PutIn(sack_slot, chest)));
+        assert_eq!(world.bag_capacity(sack), 10);
+
+        world.update(&Command::TakeOut(sword));
+        assert_eq!(world.entity_parent(sword), Some(player));
+        assert!(world.is_empty(chest));
+
+        // Out of reach containers can't be used.
+        world.place_entity(chest, loc.jump(&world, calx::Dir6::North));
+        wait_for_turn(&mut world);
+        let slot = world.entity_slot(sword).unwrap();
+        assert!(!world.can_command(&Command::PutIn(slot, chest)));
+        assert!(world.can_command(&Command::PutIn(slot, sack)));
+    }
+}
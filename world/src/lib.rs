@@ -13,6 +13,8 @@ mod components;
 mod desc;
 pub use desc::Icon;
 
+mod detect;
+
 mod effect;
 pub use effect::Ability;
 
@@ -20,11 +22,17 @@ mod extract;
 pub use extract::ExternalEntity;
 
 mod flags;
+pub use flags::DEFAULT_ACTIVE_RADIUS;
 
 mod fov;
 
 mod grammar;
 
+mod hunger;
+pub use hunger::Hunger;
+
+mod identify;
+
 mod item;
 pub use item::{ItemType, Slot};
 
@@ -38,6 +46,8 @@ pub use mapsave::WorldData;
 
 mod map;
 
+mod pathing;
+
 mod movement;
 
 mod msg;
@@ -50,13 +60,22 @@ mod query;
 mod sector;
 pub use sector::{Sector, SectorDir, SectorVec, WorldSkeleton, SECTOR_HEIGHT, SECTOR_WIDTH};
 
+mod shop;
+mod sound;
 mod spatial;
 mod spec;
+pub use spec::{load_specs, SpecError};
 mod stats;
+pub use stats::Status;
 
 pub mod terrain;
 pub use terrain::Terrain;
 
+#[cfg(test)]
+mod test_util;
+
+mod trap;
+
 mod vaults;
 
 mod volume;
@@ -183,6 +183,23 @@ impl Map {
                     cell.spawns.push(EntitySpawn::from_str("dreg").unwrap());
                 }
 
+                '^' => {
+                    cell.terrain = Ground;
+                    cell.spawns.push(EntitySpawn::from_str("trap").unwrap());
+                }
+
+                'k' => {
+                    cell.terrain = Ground;
+                    cell.spawns
+                        .push(EntitySpawn::from_str("shopkeeper").unwrap());
+                }
+
+                '$' => {
+                    cell.terrain = Ground;
+                    // Chests get filled with loot when they're spawned in the world.
+                    cell.spawns.push(EntitySpawn::from_str("chest").unwrap());
+                }
+
                 c => {
                     die!("Unknown map glyph '{}'", c);
                 }
@@ -1,9 +1,10 @@
 //! Logic for movement and game world space
 use crate::{
+    sound::Noise,
     stats::{Intrinsic, Status},
-    ActionOutcome, Location, Sector, World,
+    ActionOutcome, Location, Sector, Terrain, World,
 };
-use calx::{Clamp, Dir6, RngExt};
+use calx::{hex_neighbors, CellVector, Clamp, Dir6, RngExt};
 use calx_ecs::Entity;
 use rand::Rng;
 
@@ -44,17 +45,38 @@ impl World {
     pub(crate) fn really_step(&mut self, e: Entity, dir: Dir6) -> ActionOutcome {
         let origin = self.location(e)?;
         let loc = origin.jump(self, dir);
+        if self.terrain(loc).is_door() && self.has_intrinsic(e, Intrinsic::Hands) {
+            // Bumping into a closed door opens it.
+            return self.open_door(e, loc);
+        }
+        if let Some(mob) = self.mob_at(loc) {
+            if self.leader(mob) == Some(e)
+                && self.can_enter_terrain(e, loc)
+                && self.can_enter_terrain(mob, origin)
+            {
+                // Allies get out of their leader's way.
+                self.place_entity(mob, origin);
+                self.tween(mob, loc);
+            }
+        }
         if self.can_enter(e, loc) {
             self.place_entity(e, loc);
 
-            let delay = self.action_delay(e);
-            debug_assert!(delay > 0);
-            let anim_tick = self.get_anim_tick();
-            if let Some(anim) = self.ecs_mut().anim.get_mut(e) {
-                anim.tween_from = origin;
-                anim.tween_start = anim_tick;
-                anim.tween_duration = delay;
+            if self.portal(origin + CellVector::from(dir)).is_some() {
+                self.bring_followers(e, origin, loc);
+            }
+
+            self.tween(e, origin);
+            self.make_noise(loc, Noise::Footsteps, Some(e));
+
+            if self.terrain(loc) == Terrain::Shallows {
+                // Wading puts out flames.
+                self.remove_status(e, Status::Burning);
             }
+
+            self.step_triggers(e, loc);
+            self.search(e, 1.0);
+
             self.end_turn(e);
             return Some(true);
         }
@@ -62,6 +84,77 @@ impl World {
         None
     }
 
+    /// Animate an entity moving from a previous location to its current one.
+    fn tween(&mut self, e: Entity, from: Location) {
+        let delay = self.action_delay(e);
+        debug_assert!(delay > 0);
+        let anim_tick = self.get_anim_tick();
+        if let Some(anim) = self.ecs_mut().anim.get_mut(e) {
+            anim.tween_from = from;
+            anim.tween_start = anim_tick;
+            anim.tween_duration = delay;
+        }
+    }
+
+    /// Bring followers next to the leader's old location along when the leader moves far away.
+    pub(crate) fn bring_followers(&mut self, leader: Entity, origin: Location, destination: Location) {
+        const ARRIVAL_RADIUS: i32 = 2;
+
+        let followers: Vec<Entity> = hex_neighbors(origin)
+            .filter_map(|loc| self.mob_at(loc))
+            .filter(|&mob| self.leader(mob) == Some(leader))
+            .collect();
+
+        for mob in followers {
+            let spot = self
+                .fov_from(destination, ARRIVAL_RADIUS)
+                .into_iter()
+                .find(|&loc| self.can_enter(mob, loc) && self.portal(loc).is_none());
+            if let Some(loc) = spot {
+                self.place_entity(mob, loc);
+            }
+        }
+    }
+
+    /// Open a closed door at location.
+    fn open_door(&mut self, e: Entity, loc: Location) -> ActionOutcome {
+        if !self.terrain(loc).is_door() {
+            return None;
+        }
+
+        self.set_terrain(loc, Terrain::OpenDoor);
+        self.make_noise(loc, Noise::Door, Some(e));
+        self.end_turn(e);
+        Some(true)
+    }
+
+    /// Close an open door next to the entity.
+    pub(crate) fn entity_close(&mut self, e: Entity, dir: Dir6) -> ActionOutcome {
+        if !self.can_close(e, dir) {
+            return None;
+        }
+        let loc = self.location(e)?.jump(self, dir);
+
+        self.set_terrain(loc, Terrain::Door);
+        self.make_noise(loc, Noise::Door, Some(e));
+        self.end_turn(e);
+        Some(true)
+    }
+
+    /// Return whether the entity can close a door in a direction.
+    ///
+    /// The doorway must be empty of mobs and items.
+    pub fn can_close(&self, e: Entity, dir: Dir6) -> bool {
+        if !self.has_intrinsic(e, Intrinsic::Hands) {
+            return false;
+        }
+        let loc = match self.location(e) {
+            Some(loc) => loc.jump(self, dir),
+            None => return false,
+        };
+        self.terrain(loc) == Terrain::OpenDoor && self.entities_at(loc).is_empty()
+    }
+
     /// Randomly make a confused mob move erratically.
     ///
     /// Return true if confusion kicked in.
@@ -110,6 +203,8 @@ impl World {
     pub fn blocks_sight(&self, loc: Location) -> bool { self.terrain(loc).blocks_sight() }
 
     /// Return whether the entity can occupy a location.
+    ///
+    /// Closed doors count as enterable for entities that can open them.
     pub fn can_enter(&self, e: Entity, loc: Location) -> bool {
         if self.terrain(loc).is_door() && !self.has_intrinsic(e, Intrinsic::Hands) {
             // Can't open doors without hands.
@@ -200,3 +295,75 @@ impl World {
         1.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        test_util::{test_world, wait_for_turn},
+        Command, ExternalEntity, Terrain, World,
+    };
+    use calx::{Dir6, Incremental};
+
+    #[test]
+    fn test_doors() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let origin = world.location(player).unwrap();
+        let dir = Dir6::North;
+        let loc = origin.jump(&world, dir);
+        world.set_terrain(loc, Terrain::Door);
+
+        // Bumping into a door opens it without moving you.
+        assert!(!world.can_close(player, dir));
+        world.update(&Command::Step(dir));
+        assert_eq!(world.terrain(loc), Terrain::OpenDoor);
+        assert_eq!(world.location(player), Some(origin));
+        wait_for_turn(&mut world);
+
+        // Can't close a door on someone.
+        let dog = world.spawn(&ExternalEntity::from_name("dog").unwrap(), loc);
+        assert!(!world.can_command(&Command::Close(dir)));
+        world.update(&Command::Close(dir));
+        assert_eq!(world.terrain(loc), Terrain::OpenDoor);
+
+        world.kill_entity(dog);
+        assert!(world.can_command(&Command::Close(dir)));
+        world.update(&Command::Close(dir));
+        assert_eq!(world.terrain(loc), Terrain::Door);
+
+        // Door states are saved.
+        world.set_terrain(origin.jump(&world, Dir6::South), Terrain::OpenDoor);
+        let saved = ron::ser::to_string(&world).unwrap();
+        let loaded: World = ron::de::from_str(&saved).unwrap();
+        assert_eq!(loaded.terrain(loc), Terrain::Door);
+        assert_eq!(
+            loaded.terrain(origin.jump(&world, Dir6::South)),
+            Terrain::OpenDoor
+        );
+    }
+
+    #[test]
+    fn test_swap_with_follower() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let origin = world.location(player).unwrap();
+        let dir = *Dir6::iter()
+            .find(|&&dir| world.can_enter(player, origin.jump(&world, dir)))
+            .unwrap();
+        let loc = origin.jump(&world, dir);
+
+        let dog = world.spawn(&ExternalEntity::from_name("dog").unwrap(), loc);
+        world.make_ally(dog, player);
+
+        // Can't trade places with a follower standing somewhere you can't go.
+        world.set_terrain(loc, Terrain::Water);
+        assert!(world.really_step(player, dir).is_none());
+        assert_eq!(world.location(player), Some(origin));
+        assert_eq!(world.location(dog), Some(loc));
+
+        world.set_terrain(loc, Terrain::Ground);
+        assert!(world.really_step(player, dir).is_some());
+        assert_eq!(world.location(player), Some(loc));
+        assert_eq!(world.location(dog), Some(origin));
+    }
+}
@@ -1,13 +1,15 @@
 //! Gameplay logic that changes things
 
 use crate::{
-    ai::Brain,
+    ai::{Alignment, Brain},
     effect::{Damage, Effect},
     msg,
     sector::SECTOR_WIDTH,
-    stats::Status,
+    sound::Noise,
+    stats::{Status, Susceptibility},
     volume::Volume,
-    Ability, ActionOutcome, Anim, AnimState, Ecs, ExternalEntity, Location, Slot, World,
+    Ability, ActionOutcome, Anim, AnimState, Ecs, ExternalEntity, ItemType, Location, Slot,
+    World,
 };
 use calx::Dir6;
 use calx_ecs::Entity;
@@ -43,6 +45,14 @@ impl World {
     pub(crate) fn equip_item(&mut self, e: Entity, parent: Entity, slot: Slot) {
         self.spatial.equip(e, parent, slot);
         self.rebuild_stats(parent);
+
+        // Curses make themselves known when the item is put on.
+        if slot.is_equipment_slot() && self.is_cursed(e) && self.is_player(parent) {
+            let item = self.object(e);
+            if self.reveal_curse(e) {
+                msg!("[Another] is cursed!"; self.subject(parent), item);
+            }
+        }
     }
 
     pub(crate) fn set_player(&mut self, player: Option<Entity>) { self.flags.player = player; }
@@ -128,9 +138,17 @@ impl World {
         }
     }
 
+    pub(crate) fn entity_shoot(&mut self, e: Entity, dir: Dir6) -> ActionOutcome {
+        if self.confused_move(e) {
+            Some(true)
+        } else {
+            self.really_shoot(e, dir)
+        }
+    }
+
     /// The entity spends its action waiting.
     pub(crate) fn idle(&mut self, e: Entity) -> ActionOutcome {
-        if self.consume_nutrition(e) {
+        if self.can_regenerate(e) {
             if let Some(_regen) = self.tick_regeneration(e) {
                 // TODO: animate/message the healing.
             }
@@ -165,6 +183,7 @@ impl World {
             // Playerify with the boring component stuff.
             self.ecs_mut().brain.insert(player, Brain::player());
             self.ecs_mut().map_memory.insert(player, Default::default());
+            self.ecs_mut().satiation.insert(player, Default::default());
             self.set_player(Some(player));
             self.place_entity(player, loc);
         }
@@ -180,10 +199,21 @@ impl World {
         match *effect {
             Hit { amount, damage } => {
                 self.damage(target, amount as i32, damage, source);
+                // Fire sets things alight, lightning leaves them reeling.
+                if let Some((status, duration)) = damage.aftereffect() {
+                    if self.is_alive(target)
+                        && self.susceptibility(target, damage) != Susceptibility::Immune
+                    {
+                        self.gain_status(target, status, duration);
+                    }
+                }
             }
             Confuse => {
                 self.gain_status(target, Status::Confused, 40);
-                msg!("[One] [is] confused."; self.subject(target));
+            }
+            Drain { amount, lethal } => {
+                msg!("[One] feel[s] [one's] life force draining away."; self.subject(target));
+                self.drain_xp(target, amount, lethal, source);
             }
         }
     }
@@ -216,11 +246,32 @@ impl World {
     /// is run for player and AI entities.
     pub(crate) fn heartbeat(&mut self, e: Entity) { self.tick_statuses(e); }
 
-    pub(crate) fn use_ability(&mut self, _e: Entity, _a: Ability) -> ActionOutcome {
-        // TODO
-        None
+    /// Teach an entity an innate ability.
+    ///
+    /// Returns false if the entity already knows the ability. The player's hotbar picks up newly
+    /// learned abilities by itself.
+    pub(crate) fn learn_ability(&mut self, e: Entity, ability: Ability) -> bool {
+        if self.has_ability(e, ability) {
+            return false;
+        }
+        if let Some(abilities) = self.ecs_mut().ability.get_mut(e) {
+            abilities.push(ability);
+        } else {
+            self.ecs_mut().ability.insert(e, vec![ability]);
+        }
+        true
+    }
+
+    /// Use an innate untargeted ability of the entity.
+    pub(crate) fn use_ability(&mut self, e: Entity, a: Ability) -> ActionOutcome {
+        debug_assert!(!a.is_targeted());
+        if !self.has_ability(e, a) {
+            return None;
+        }
+        self.resolve_ability(e, a, None, None)
     }
 
+    /// Use an untargeted ability granted by an item.
     pub(crate) fn use_item_ability(
         &mut self,
         e: Entity,
@@ -228,10 +279,83 @@ impl World {
         a: Ability,
     ) -> ActionOutcome {
         debug_assert!(!a.is_targeted());
-        // TODO: Lift to generic ability use method
-        if !self.has_ability(item, a) {
+        if !self.has_ability(item, a) || self.is_empty_item(e, item) {
+            return None;
+        }
+        let ret = self.resolve_ability(e, a, None, Some(item))?;
+        self.identify_by_use(e, item);
+        self.drain_charge(e, item);
+        Some(ret)
+    }
+
+    /// Check for an item with no charges left, tell the player about it.
+    fn is_empty_item(&self, e: Entity, item: Entity) -> bool {
+        if self.uses_left(item) > 0 {
+            return false;
+        }
+        if self.is_player(e) {
+            msg!("Nothing happens, [another] is empty."; self.subject(e), self.object(item));
+        }
+        true
+    }
+
+    /// Use an innate targeted ability of the entity.
+    pub(crate) fn use_targeted_ability(
+        &mut self,
+        e: Entity,
+        a: Ability,
+        dir: Dir6,
+    ) -> ActionOutcome {
+        debug_assert!(a.is_targeted());
+        if !self.has_ability(e, a) {
+            return None;
+        }
+        self.resolve_ability(e, a, Some(dir), None)
+    }
+
+    /// Use a targeted ability granted by an item.
+    pub(crate) fn use_targeted_item_ability(
+        &mut self,
+        e: Entity,
+        item: Entity,
+        a: Ability,
+        dir: Dir6,
+    ) -> ActionOutcome {
+        debug_assert!(a.is_targeted());
+        if !self.has_ability(item, a) || self.is_empty_item(e, item) {
             return None;
         }
+        let ret = self.resolve_ability(e, a, Some(dir), Some(item))?;
+        self.identify_by_use(e, item);
+        self.drain_charge(e, item);
+        Some(ret)
+    }
+
+    /// Carry out the effects of an ability used by entity.
+    ///
+    /// Shared by innate and item abilities, the caller is responsible for checking that the
+    /// ability is available. Targeted abilities must be given a direction, abilities that act on
+    /// the item itself, like eating, must be given the item.
+    fn resolve_ability(
+        &mut self,
+        e: Entity,
+        a: Ability,
+        dir: Option<Dir6>,
+        item: Option<Entity>,
+    ) -> ActionOutcome {
+        self.ability_effect(e, a, dir, item)?;
+        self.end_turn(e);
+        Some(true)
+    }
+
+    /// Apply the effects of an ability without spending the entity's turn.
+    pub(crate) fn ability_effect(
+        &mut self,
+        e: Entity,
+        a: Ability,
+        dir: Option<Dir6>,
+        item: Option<Entity>,
+    ) -> Option<()> {
         let origin = self.location(e)?;
 
         match a {
@@ -260,40 +384,6 @@ impl World {
                     msg!("The spell fizzles.");
                 }
             }
-            _ => {
-                msg!("TODO cast untargeted spell {:?}", a);
-            }
-        }
-        self.drain_charge(item);
-        Some(true)
-    }
-
-    pub(crate) fn use_targeted_ability(
-        &mut self,
-        _e: Entity,
-        _a: Ability,
-        _dir: Dir6,
-    ) -> ActionOutcome {
-        // TODO
-        None
-    }
-
-    pub(crate) fn use_targeted_item_ability(
-        &mut self,
-        e: Entity,
-        item: Entity,
-        a: Ability,
-        dir: Dir6,
-    ) -> ActionOutcome {
-        debug_assert!(a.is_targeted());
-        if !self.has_ability(item, a) {
-            return None;
-        }
-        let origin = self.location(e)?;
-
-        // TODO: Lift to generic ability use method
-
-        match a {
             Ability::Fireball => {
                 const FIREBALL_RANGE: u32 = 9;
                 const FIREBALL_RADIUS: u32 = 1;
@@ -301,9 +391,11 @@ impl World {
                     amount: 6,
                     damage: Damage::Fire,
                 };
-                let center = self.projected_explosion_center(origin, dir, FIREBALL_RANGE);
+                let center = self.projected_explosion_center(origin, dir?, FIREBALL_RANGE);
+                msg!("[One] hurl[s] a fireball."; self.subject(e));
                 let volume = self.sphere_volume(center, FIREBALL_RADIUS);
                 self.apply_effect(&FIREBALL_EFFECT, &volume, Some(e));
+                self.make_noise(center, Noise::Explosion, Some(e));
 
                 // TODO: Maybe move anim generation to own procedure?
                 const PROJECTILE_TIME: u64 = 8;
@@ -324,14 +416,174 @@ impl World {
             Ability::Confuse => {
                 const CONFUSION_RANGE: u32 = 9;
 
-                let center = self.projected_explosion_center(origin, dir, CONFUSION_RANGE);
+                let center = self.projected_explosion_center(origin, dir?, CONFUSION_RANGE);
                 self.apply_effect(&Effect::Confuse, &Volume::point(center), Some(e));
             }
-            _ => {
-                msg!("TODO cast directed spell {:?}", a);
+            Ability::Eat => {
+                self.eat(e, item?)?;
+            }
+            Ability::Study => {
+                let taught = match self.item_type(item?) {
+                    Some(ItemType::Spellbook(ability)) => ability,
+                    _ => return None,
+                };
+                if !self.learn_ability(e, taught) {
+                    msg!("[One] already know[s] that spell."; self.subject(e));
+                    return None;
+                }
+                msg!("[One] learn[s] a new spell."; self.subject(e));
+            }
+            Ability::Tame => {
+                let tamed: Vec<Entity> = Dir6::iter()
+                    .filter_map(|&dir| self.mob_at(origin.jump(self, dir)))
+                    .filter(|&mob| self.alignment(mob) == Some(Alignment::Animal))
+                    .collect();
+
+                if tamed.is_empty() {
+                    msg!("Nothing seems to happen.");
+                }
+                for mob in tamed {
+                    msg!("[One] look[s] friendly."; self.subject(mob));
+                    self.make_ally(mob, e);
+                }
+            }
+            Ability::Recharge => {
+                let mut recharged = false;
+                for (_, item) in self.entities_in(e) {
+                    recharged |= self.recharge(item);
+                }
+
+                if recharged {
+                    msg!("[One's] belongings hum with renewed power."; self.subject(e));
+                } else {
+                    msg!("Nothing seems to happen.");
+                }
+            }
+            Ability::RemoveCurse => {
+                let mut lifted = false;
+                for (_, item) in self.entities_in(e) {
+                    lifted |= self.uncurse(item);
+                }
+
+                if lifted {
+                    msg!("A malevolent aura lifts from [one's] belongings."; self.subject(e));
+                } else {
+                    msg!("Nothing seems to happen.");
+                }
+            }
+            Ability::MagicMap => {
+                if self.magic_map(e) {
+                    msg!("A map of the surroundings forms in [one's] mind."; self.subject(e));
+                } else {
+                    msg!("Nothing seems to happen.");
+                }
+            }
+            Ability::DetectMonsters => {
+                const DETECT_MONSTERS_DURATION: u64 = 240;
+
+                if self.detect(e, DETECT_MONSTERS_DURATION, |w, x| w.is_mob(x)) {
+                    msg!("[One] sense[s] the presence of creatures."; self.subject(e));
+                } else {
+                    msg!("[One] feel[s] alone."; self.subject(e));
+                }
+            }
+            Ability::DetectItems => {
+                const DETECT_ITEMS_DURATION: u64 = 1200;
+
+                if self.detect(e, DETECT_ITEMS_DURATION, |w, x| w.is_item(x)) {
+                    msg!("[One] sense[s] the presence of objects."; self.subject(e));
+                } else {
+                    msg!("Nothing seems to happen.");
+                }
+            }
+            Ability::Heal => {
+                if let Some(health) = self.ecs_mut().health.get_mut(e) {
+                    health.wounds = 0;
+                }
+                msg!("[One] feel[s] better."; self.subject(e));
+            }
+            Ability::Regenerate => {
+                const REGENERATION_DURATION: u32 = 120;
+
+                self.gain_status(e, Status::Regenerating, REGENERATION_DURATION);
+            }
+            Ability::Invisibility => {
+                const INVISIBILITY_DURATION: u32 = 240;
+
+                self.gain_status(e, Status::Invisible, INVISIBILITY_DURATION);
             }
         }
-        self.drain_charge(item);
-        Some(true)
+
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        stats::Status,
+        test_util::{give_item, test_world},
+        Ability, Command, ExternalEntity, Terrain,
+    };
+    use calx::{Dir6, Incremental};
+
+    #[test]
+    fn test_innate_abilities() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        world.ecs_mut().stats[player].base.base_power = 100;
+        world.rebuild_stats(player);
+        let dir = Dir6::North;
+        let mut line = Vec::new();
+        let mut loc = world.location(player).unwrap();
+        for _ in 0..4 {
+            loc = loc.jump(&world, dir);
+            world.set_terrain(loc, Terrain::Ground);
+            line.push(loc);
+        }
+
+        // Abilities must be learned before they can be used.
+        assert!(world.use_ability(player, Ability::Regenerate).is_none());
+        assert!(world.learn_ability(player, Ability::Regenerate));
+        assert!(!world.learn_ability(player, Ability::Regenerate));
+        assert_eq!(world.list_abilities(player), vec![Ability::Regenerate]);
+        assert!(world.use_ability(player, Ability::Regenerate).is_some());
+        assert!(world.has_status(player, Status::Regenerating));
+
+        // Reading a spellbook teaches its spell and uses up the book.
+        let book = give_item(&mut world, player, "spellbook of fireball");
+        assert!(!world.has_ability(player, Ability::Fireball));
+        world.update(&Command::UntargetedAbility {
+            ability: Ability::Study,
+            item: Some(book),
+        });
+        assert!(world.has_ability(player, Ability::Fireball));
+        assert!(!world.is_alive(book));
+
+        // Known spells can't be learned again, so the book is kept.
+        let book = give_item(&mut world, player, "spellbook of fireball");
+        world.update(&Command::UntargetedAbility {
+            ability: Ability::Study,
+            item: Some(book),
+        });
+        assert!(world.is_alive(book));
+
+        let ogre = world.spawn(&ExternalEntity::from_name("ogre").unwrap(), line[2]);
+        assert!(world
+            .use_targeted_ability(player, Ability::Fireball, dir)
+            .is_some());
+        assert!(world.hp(ogre) < world.max_hp(ogre));
+        world.kill_entity(ogre);
+
+        // Efreet cast fireballs from their spec abilities.
+        let efreet = world.spawn(&ExternalEntity::from_name("efreeti").unwrap(), line[3]);
+        assert!(world.has_ability(efreet, Ability::Fireball));
+        assert!(world
+            .use_targeted_ability(efreet, Ability::Confuse, dir + 3)
+            .is_none());
+        assert!(world
+            .use_targeted_ability(efreet, Ability::Fireball, dir + 3)
+            .is_some());
+        assert!(world.hp(player) < world.max_hp(player));
     }
 }
@@ -0,0 +1,212 @@
+//! Cached pathfinding maps for mob movement
+
+use crate::{stats::Intrinsic, Location, World};
+use calx::{CellVector, Dijkstra, Dir6};
+use calx_ecs::Entity;
+use std::collections::HashMap;
+
+/// How far from the target the pathfinding maps extend.
+const PATHING_RANGE: u32 = 24;
+
+/// Cache of Dijkstra maps leading towards specific entities.
+///
+/// The maps are derived data and are not saved, they get regenerated on demand. The cache is
+/// only used for lookups and never iterated over, so the hash map in here does not affect
+/// gameplay determinism.
+#[derive(Default)]
+pub(crate) struct PathCache {
+    /// Maps keyed by (target entity, whether the map goes through doors), along with the location
+    /// the target was in when the map was built.
+    maps: HashMap<(Entity, bool), (Location, Dijkstra<Location>)>,
+    /// Inverted maps for running away from entities, keyed the same way.
+    flee_maps: HashMap<(Entity, bool), (Location, Dijkstra<Location>)>,
+}
+
+impl PathCache {
+    pub fn clear(&mut self) {
+        self.maps.clear();
+        self.flee_maps.clear();
+    }
+}
+
+impl World {
+    /// Find the next step for a mob moving towards a target entity using a Dijkstra map.
+    ///
+    /// Returns `None` if the mob is already next to the target or if there is no open step that
+    /// gets it closer. Falls back to `pathing_dir_towards` if the mob is too far from the target
+    /// to be covered by the map.
+    pub(crate) fn pathing_dir_to_entity(&self, e: Entity, target: Entity) -> Option<Dir6> {
+        let origin = self.location(e)?;
+        let target_loc = self.location(target)?;
+        let through_doors = self.has_intrinsic(e, Intrinsic::Hands);
+
+        self.update_path_map(target, target_loc, through_doors);
+
+        let cache = self.path_cache.borrow();
+        let (_, map) = &cache.maps[&(target, through_doors)];
+
+        let current = match map.weights.get(&origin) {
+            Some(&w) => w,
+            None => return self.pathing_dir_towards(e, target_loc),
+        };
+
+        // Go through directions in fixed order, `min_by_key` picks the first of equally good
+        // steps, so tie-breaking is deterministic.
+        Dir6::iter()
+            .filter_map(|&dir| {
+                let next_loc = origin.jump(self, dir);
+                let &w = map.weights.get(&next_loc)?;
+                if w < current && self.can_enter(e, next_loc) {
+                    Some((w, dir))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|&(w, _)| w)
+            .map(|(_, dir)| dir)
+    }
+
+    /// Find the next step for a mob running away from a threat entity.
+    ///
+    /// Uses an inverted Dijkstra map, so the mob will try to get around the threat into open
+    /// space instead of backing into a corner. Returns `None` if there's no step that improves the
+    /// mob's situation or if the mob is already out of the threat's map range.
+    pub(crate) fn pathing_dir_away_from_entity(&self, e: Entity, threat: Entity) -> Option<Dir6> {
+        let origin = self.location(e)?;
+        let threat_loc = self.location(threat)?;
+        let through_doors = self.has_intrinsic(e, Intrinsic::Hands);
+
+        self.update_flee_map(threat, threat_loc, through_doors);
+
+        let cache = self.path_cache.borrow();
+        let (_, map) = &cache.flee_maps[&(threat, through_doors)];
+
+        let &current = map.weights.get(&origin)?;
+
+        Dir6::iter()
+            .filter_map(|&dir| {
+                let next_loc = origin.jump(self, dir);
+                let &w = map.weights.get(&next_loc)?;
+                if w < current && self.can_enter(e, next_loc) {
+                    Some((w, dir))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|&(w, _)| w)
+            .map(|(_, dir)| dir)
+    }
+
+    /// Drop all cached pathfinding maps.
+    ///
+    /// Must be called whenever terrain changes in a way that affects walkability.
+    pub fn invalidate_paths(&self) { self.path_cache.borrow_mut().clear(); }
+
+    /// Make sure there is an up-to-date path map for target in cache.
+    fn update_path_map(&self, target: Entity, target_loc: Location, through_doors: bool) {
+        let key = (target, through_doors);
+        if let Some((loc, _)) = self.path_cache.borrow().maps.get(&key) {
+            if *loc == target_loc {
+                return;
+            }
+        }
+
+        let map = Dijkstra::with_neighbors(
+            vec![target_loc],
+            |loc| self.path_predecessors(loc),
+            |&loc| {
+                !self.terrain_blocks_walk(loc) && (through_doors || !self.terrain(loc).is_door())
+            },
+            PATHING_RANGE,
+        );
+
+        let mut cache = self.path_cache.borrow_mut();
+        // Clean out maps for targets that are no longer around.
+        cache.maps.retain(|&(e, _), _| self.location(e).is_some());
+        cache.maps.insert(key, (target_loc, map));
+    }
+
+    /// Make sure there is an up-to-date flee map for target in cache.
+    fn update_flee_map(&self, target: Entity, target_loc: Location, through_doors: bool) {
+        let key = (target, through_doors);
+        if let Some((loc, _)) = self.path_cache.borrow().flee_maps.get(&key) {
+            if *loc == target_loc {
+                return;
+            }
+        }
+
+        self.update_path_map(target, target_loc, through_doors);
+        let map = self.path_cache.borrow().maps[&key]
+            .1
+            .inverted(|loc| self.path_predecessors(loc));
+
+        let mut cache = self.path_cache.borrow_mut();
+        cache
+            .flee_maps
+            .retain(|&(e, _), _| self.location(e).is_some());
+        cache.flee_maps.insert(key, (target_loc, map));
+    }
+
+    /// Cells from which a single step leads into location, following portals backwards.
+    ///
+    /// Dijkstra maps grow outwards from the target, so they need to go against the direction of
+    /// movement. Portals can be one-way, so the cells a step from location leads into won't do.
+    fn path_predecessors(&self, loc: &Location) -> Vec<Location> {
+        // Cells that take you here when stepped into, the location itself and any portals to it.
+        let mut entrances = self.world_cache.get_portal_origins(*loc);
+        entrances.push(*loc);
+
+        let mut ret = Vec::new();
+        for &dir in Dir6::iter() {
+            for &a in &entrances {
+                let prev = a - CellVector::from(dir);
+                if prev.jump(self, dir) == *loc {
+                    ret.push(prev);
+                }
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_util::test_world, ExternalEntity, Location, Terrain};
+    use calx::Dir6;
+
+    #[test]
+    fn test_one_way_portal() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let target = world.location(player).unwrap();
+        let mut open = Dir6::iter()
+            .map(|&dir| target.jump(&world, dir))
+            .filter(|&loc| world.can_enter(player, loc));
+        let (exit, entrance) = (open.next().unwrap(), open.next().unwrap());
+
+        // Spots off in the distance with portals next to them.
+        let far = |dx| Location {
+            x: target.x + dx,
+            ..target
+        };
+        let (mob_loc, mob_portal) = (far(12), far(13));
+        let (other_loc, other_exit) = (far(-12), far(-13));
+        for &loc in &[mob_loc, other_loc, other_exit] {
+            world.set_terrain(loc, Terrain::Ground);
+        }
+        let mob = world.spawn(&ExternalEntity::from_name("rat").unwrap(), mob_loc);
+
+        // One portal leads from next to the mob to next to the target, the other one from next to
+        // the target to the other spot.
+        world.world_cache.portal(mob_portal, exit);
+        world.world_cache.portal(entrance, other_exit);
+        world.invalidate_paths();
+
+        assert_eq!(world.pathing_dir_to_entity(mob, player), Some(Dir6::Southeast));
+        let cache = world.path_cache.borrow();
+        let (_, map) = &cache.maps[&(player, false)];
+        assert_eq!(map.weights.get(&mob_loc), Some(&2));
+        // The other portal can't be used to get to the target, so it's no shortcut.
+        assert!(map.weights.get(&other_loc).map_or(true, |&w| w >= 12));
+    }
+}
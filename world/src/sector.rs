@@ -8,8 +8,8 @@ use crate::{
     vaults, {Distribution, Rng},
 };
 use calx::{
-    die, project, seeded_rng, CellSpace, CellVector, ProjectVec, RngExt, Space, StaggeredHexSpace,
-    WeightedChoice,
+    die, project, seeded_rng, CellSpace, CellVector, HexGeom, ProjectVec, RngExt, Space,
+    StaggeredHexSpace, WeightedChoice,
 };
 use euclid::{vec2, vec3, Vector2D};
 use lazy_static::lazy_static;
@@ -231,6 +231,15 @@ impl Sector {
     /// Default camera position.
     pub fn center(self) -> Location { Location::from(self) + vec2(SECTOR_HEX_SIDE - 1, 0) }
 
+    /// Hex distance in sector space from this sector to the other one, if they are on the same
+    /// floor.
+    pub fn distance_from(self, other: Sector) -> Option<i32> {
+        if self.z != other.z {
+            return None;
+        }
+        Some(CellVector::new((other.x - self.x) as i32, (other.y - self.y) as i32).hex_dist())
+    }
+
     pub fn iter(self) -> impl Iterator<Item = Location> {
         let origin = Location::from(self);
         Sector::shape().map(move |p| origin + p)
@@ -759,6 +768,12 @@ impl<'a> ConnectedSectorSpec<'a> {
         // TODO: Add enclosures
         self.place_stairwells(&mut map);
 
+        if self.biome == Biome::City {
+            // Cities have a shop, if one fits.
+            let shop = vaults::SHOPS.choose(rng).unwrap();
+            let _ = map.place_room(rng, shop);
+        }
+
         for &pos in &map.open_ground() {
             // TODO: Pick distribution based on biome...
             if let Some(spawn) = self.sample(rng) {
@@ -772,6 +787,21 @@ impl<'a> ConnectedSectorSpec<'a> {
     fn can_spawn(&self, spec: &dyn Spec) -> bool {
         spec.min_depth() <= self.depth && (spec.habitat() & (1 << self.biome as u64)) != 0
     }
+
+    /// Pick a random item to put in a container generated in this sector.
+    ///
+    /// Loot only cares about depth, not habitat, so containers can hold dungeon items anywhere.
+    pub fn sample_loot(&self, rng: &mut Rng) -> Option<EntitySpawn> {
+        let item = spec::iter_specs().weighted_choice(rng, |item| {
+            if item.rarity() == 0.0 || !item.is_loot() || item.min_depth() > self.depth.max(0) {
+                0.0
+            } else {
+                1.0 / item.rarity()
+            }
+        })?;
+
+        Some(EntitySpawn::from_str(item.id()).unwrap())
+    }
 }
 
 impl Distribution<EntitySpawn> for ConnectedSectorSpec<'_> {
@@ -854,6 +884,19 @@ mod test {
     use calx::{CellSpace, ProjectVec, StaggeredHexSpace};
     use euclid::{vec2, vec3};
 
+    #[test]
+    fn test_sector_distance() {
+        let origin = Sector::new(0, 0, 0);
+        assert_eq!(Some(0), origin.distance_from(origin));
+        assert_eq!(Some(1), origin.distance_from(Sector::new(1, 1, 0)));
+        assert_eq!(Some(2), origin.distance_from(Sector::new(1, -1, 0)));
+        assert_eq!(
+            Some(3),
+            Sector::new(-1, -1, 0).distance_from(Sector::new(2, 2, 0))
+        );
+        assert_eq!(None, origin.distance_from(Sector::new(0, 0, -1)));
+    }
+
     #[test]
     fn test_rect_space() {
         let shape: Vec<CellVector> = Sector::shape().collect();
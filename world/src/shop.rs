@@ -0,0 +1,250 @@
+//! Money, shopkeepers and trading
+
+use crate::{msg, stats::Intrinsic, ActionOutcome, ExternalEntity, ItemType, World};
+use calx::Dir6;
+use calx_ecs::Entity;
+
+/// Spec name of the currency item.
+const GOLD: &str = "gold piece";
+
+/// Shopkeepers buy items for this fraction of what they sell them for.
+const SELL_PRICE_DIVISOR: u32 = 2;
+
+/// Create a stack of gold pieces.
+///
+/// Return `None` for zero gold or spec data without money.
+pub(crate) fn gold_pieces(amount: u32) -> Option<ExternalEntity> {
+    if amount == 0 {
+        return None;
+    }
+    let mut gold = ExternalEntity::from_name(GOLD).ok()?;
+    if let Some(stacking) = gold.loadout.stacking.as_mut() {
+        stacking.count = amount;
+    }
+    Some(gold)
+}
+
+impl World {
+    pub fn is_currency(&self, e: Entity) -> bool { self.item_type(e) == Some(ItemType::Currency) }
+
+    /// Return how much gold the entity is carrying.
+    pub fn gold(&self, e: Entity) -> u32 {
+        self.entities_in_bag(e)
+            .into_iter()
+            .filter(|&(_, item)| self.is_currency(item))
+            .map(|(_, item)| self.count(item))
+            .sum()
+    }
+
+    /// Return what a shopkeeper charges for an item or stack of items.
+    pub fn buy_price(&self, item: Entity) -> u32 {
+        self.ecs().item.get(item).map_or(0, |i| i.price) * self.count(item)
+    }
+
+    /// Return what a shopkeeper pays for an item or stack of items.
+    pub fn sell_price(&self, item: Entity) -> u32 { self.buy_price(item) / SELL_PRICE_DIVISOR }
+
+    pub fn is_shopkeeper(&self, e: Entity) -> bool {
+        self.is_mob(e) && self.has_intrinsic(e, Intrinsic::Shopkeeper)
+    }
+
+    /// Return whether the entity is next to a shopkeeper who is willing to trade.
+    pub fn can_trade_with(&self, e: Entity, shopkeeper: Entity) -> bool {
+        self.is_shopkeeper(shopkeeper)
+            && !self.is_hostile_to(shopkeeper, e)
+            && self.distance_between(e, shopkeeper) == Some(1)
+    }
+
+    /// Return an adjacent shopkeeper the entity can trade with.
+    pub fn adjacent_shopkeeper(&self, e: Entity) -> Option<Entity> {
+        let loc = self.location(e)?;
+        Dir6::iter()
+            .filter_map(|&dir| self.mob_at(loc.jump(self, dir)))
+            .find(|&mob| self.can_trade_with(e, mob))
+    }
+
+    /// Return the items a shopkeeper has for sale.
+    pub fn shop_stock(&self, shopkeeper: Entity) -> Vec<Entity> {
+        self.entities_in_bag(shopkeeper)
+            .into_iter()
+            .map(|(_, item)| item)
+            .filter(|&item| !self.is_currency(item))
+            .collect()
+    }
+
+    /// Return the shopkeeper who is selling the item.
+    pub fn seller_of(&self, item: Entity) -> Option<Entity> {
+        self.entity_parent(item)
+            .filter(|&parent| self.is_shopkeeper(parent) && !self.is_currency(item))
+    }
+
+    /// Take gold out of the entity's purse.
+    ///
+    /// Return false and leave the purse alone if the entity doesn't have enough gold.
+    pub(crate) fn spend_gold(&mut self, e: Entity, amount: u32) -> bool {
+        if self.gold(e) < amount {
+            return false;
+        }
+
+        let mut left = amount;
+        for (_, item) in self.entities_in_bag(e) {
+            if left == 0 {
+                break;
+            }
+            if !self.is_currency(item) {
+                continue;
+            }
+            let count = self.count(item);
+            if count <= left {
+                left -= count;
+                self.spatial.remove(item);
+            } else {
+                self.ecs_mut().stacking[item].count -= left;
+                left = 0;
+            }
+        }
+        true
+    }
+
+    /// Give the entity gold, dropping it at the entity's feet if there's no room in the bag.
+    pub(crate) fn give_gold(&mut self, e: Entity, amount: u32) {
+        let gold = match gold_pieces(amount) {
+            Some(gold) => gold,
+            None => return,
+        };
+
+        let gold = self.inject(&gold);
+        if !self.stash_item(e, gold) {
+            if let Some(loc) = self.location(e) {
+                self.place_entity(gold, loc);
+            }
+        }
+    }
+
+    /// Move gold from one entity's purse to another's.
+    ///
+    /// Return false and move nothing if the payer doesn't have enough gold.
+    pub(crate) fn pay(&mut self, payer: Entity, payee: Entity, amount: u32) -> bool {
+        if !self.spend_gold(payer, amount) {
+            return false;
+        }
+        self.give_gold(payee, amount);
+        true
+    }
+
+    pub(crate) fn buy(&mut self, e: Entity, item: Entity) -> ActionOutcome {
+        let shopkeeper = self.seller_of(item)?;
+        let price = self.buy_price(item);
+        let name = self.object(item);
+        if self.gold(e) < price || self.free_bag_slot(e).is_none() {
+            return None;
+        }
+        // Take the item before paying so the shopkeeper has room for the gold.
+        if !self.stash_item(e, item) {
+            return None;
+        }
+        self.pay(e, shopkeeper, price);
+        msg!("[One] buy[s] [a thing] for {} gold.", price; self.subject(e), name);
+        Some(false)
+    }
+
+    pub(crate) fn sell(&mut self, e: Entity, item: Entity, shopkeeper: Entity) -> ActionOutcome {
+        let price = self.sell_price(item);
+        let slot = self.free_bag_slot(shopkeeper)?;
+        if !self.pay(shopkeeper, e, price) {
+            return None;
+        }
+        msg!("[One] sell[s] [a thing] for {} gold.", price; self.subject(e), self.object(item));
+        self.equip_item(item, shopkeeper, slot);
+        Some(false)
+    }
+
+    /// Grab an item from a shopkeeper without paying.
+    ///
+    /// The shopkeeper will not take kindly to this.
+    pub(crate) fn steal(&mut self, e: Entity, item: Entity) -> ActionOutcome {
+        let shopkeeper = self.seller_of(item)?;
+        let name = self.object(item);
+        if !self.stash_item(e, item) {
+            return None;
+        }
+        msg!("[One] steal[s] [a thing]!"; self.subject(e), name);
+        self.make_hostile(shopkeeper, e);
+        self.end_turn(e);
+        Some(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        effect::Damage,
+        test_util::{give_item, test_world},
+        Command, ExternalEntity, ItemType,
+    };
+    use calx::{Dir6, Incremental};
+
+    #[test]
+    fn test_shopping() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+        let shop_loc = Dir6::iter()
+            .map(|&dir| loc.jump(&world, dir))
+            .find(|&loc| world.can_enter(player, loc))
+            .unwrap();
+
+        // Generated shopkeepers come with money to buy things with.
+        let mut generated = ExternalEntity::from_name("shopkeeper").unwrap();
+        world.fill_container(&mut generated, shop_loc, &mut calx::seeded_rng(&1));
+        assert!(generated
+            .contents
+            .values()
+            .any(|e| e.loadout.item.as_ref().is_some_and(|i| i.item_type == ItemType::Currency)));
+
+        let shopkeeper = world.spawn(&ExternalEntity::from_name("shopkeeper").unwrap(), shop_loc);
+        let sword = give_item(&mut world, shopkeeper, "sword");
+        let helmet = give_item(&mut world, shopkeeper, "helmet");
+        assert_eq!(world.adjacent_shopkeeper(player), Some(shopkeeper));
+        assert_eq!(world.seller_of(sword), Some(shopkeeper));
+
+        // Can't afford it yet.
+        let price = world.buy_price(sword);
+        assert!(price > 0);
+        assert!(!world.can_command(&Command::Buy(sword)));
+
+        world.give_gold(player, price + 5);
+        assert_eq!(world.gold(player), price + 5);
+        world.update(&Command::Buy(sword));
+        assert_eq!(world.entity_parent(sword), Some(player));
+        assert_eq!(world.gold(player), 5);
+        assert_eq!(world.gold(shopkeeper), price);
+
+        // Selling gets you less than buying costs, and the shopkeeper pays out of pocket.
+        let slot = world.entity_slot(sword).unwrap();
+        assert!(world.spend_gold(shopkeeper, price));
+        assert!(!world.can_command(&Command::Sell(slot, shopkeeper)));
+        world.give_gold(shopkeeper, price);
+        world.update(&Command::Sell(slot, shopkeeper));
+        assert_eq!(world.entity_parent(sword), Some(shopkeeper));
+        assert_eq!(world.gold(player), 5 + price / 2);
+        assert_eq!(world.gold(shopkeeper), price - price / 2);
+
+        // Stealing makes the shopkeeper turn on you.
+        assert!(!world.is_hostile_to(shopkeeper, player));
+        world.update(&Command::Steal(sword));
+        assert_eq!(world.entity_parent(sword), Some(player));
+        assert!(world.is_hostile_to(shopkeeper, player));
+        assert!(world.adjacent_shopkeeper(player).is_none());
+
+        // The rest of the stock and the purse are left behind when the shopkeeper dies.
+        world.damage(shopkeeper, 1000, Damage::Physical, Some(player));
+        assert!(!world.is_alive(shopkeeper));
+        assert!(world.entity_parent(helmet).is_none());
+        assert!(world.location(helmet).unwrap().metric_distance(shop_loc) <= 1);
+        assert!(world
+            .items_at(shop_loc)
+            .into_iter()
+            .any(|item| world.is_currency(item)));
+    }
+}
@@ -0,0 +1,142 @@
+//! Noise propagation and creatures reacting to it
+
+use crate::{ai::BrainState, ai::ShoutType, fov::SphereVolumeFov, Location, World};
+use calx::{HexFov, HexGeom, RngExt};
+use calx_ecs::Entity;
+
+/// Kinds of noise that actions in the world can make.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Noise {
+    /// A creature moving around.
+    Footsteps,
+    /// Sounds of melee fighting.
+    Combat,
+    /// A creature vocalizing.
+    Shout(ShoutType),
+    /// A door being opened or closed.
+    Door,
+    /// Something blowing up.
+    Explosion,
+    /// A trap raising the alarm.
+    Alarm,
+}
+
+impl Noise {
+    /// How far the noise carries in open space.
+    pub fn intensity(self) -> u32 {
+        use Noise::*;
+        match self {
+            Footsteps => 3,
+            Combat => 6,
+            Shout(shout) => shout.loudness(),
+            Door => 5,
+            Explosion => 12,
+            Alarm => 16,
+        }
+    }
+
+    /// Return whether the noise is only alarming when made by a creature you are hostile to.
+    ///
+    /// Sleeping monsters won't wake up to their friends walking around, but will react to a
+    /// friend's alarm shout.
+    fn needs_hostile_source(self) -> bool { self == Noise::Footsteps }
+}
+
+impl ShoutType {
+    /// Intensity of the noise made by the shout.
+    pub fn loudness(self) -> u32 {
+        use ShoutType::*;
+        match self {
+            Roar => 12,
+            Shout | Bark => 10,
+            Buzz | Gurgle | Meow => 6,
+            Hiss | Squeak => 4,
+            Silent => 0,
+        }
+    }
+}
+
+impl World {
+    /// Make a noise that spreads out from origin and may wake up creatures that hear it.
+    ///
+    /// The noise is stopped by walls and goes through visible portals like explosions do. Each
+    /// cell the noise reaches has a loudness of the noise intensity minus the distance from
+    /// origin, and sleeping creatures there have a chance proportional to the loudness to wake up
+    /// and go investigate the noise.
+    pub(crate) fn make_noise(&mut self, origin: Location, noise: Noise, source: Option<Entity>) {
+        // How loud a noise must be to always wake a sleeper up.
+        const CERTAIN_WAKEUP_LOUDNESS: f32 = 10.0;
+
+        let intensity = noise.intensity();
+        if intensity == 0 {
+            return;
+        }
+
+        let hearers: Vec<(Entity, u32)> =
+            HexFov::new(SphereVolumeFov::new(self, intensity, origin))
+                .filter_map(|(pos, a)| {
+                    let mob = self.mob_at(a.origin + pos)?;
+                    Some((mob, intensity - pos.hex_dist() as u32))
+                })
+                .collect();
+
+        for (mob, loudness) in hearers {
+            if Some(mob) == source || !self.is_npc(mob) {
+                continue;
+            }
+            if noise.needs_hostile_source()
+                && matches!(source, Some(s) if !self.is_hostile_to(mob, s))
+            {
+                continue;
+            }
+
+            match self.brain_state(mob) {
+                Some(BrainState::Asleep) | Some(BrainState::Roaming) => {}
+                _ => continue,
+            }
+
+            if self
+                .rng()
+                .with_chance(loudness as f32 / CERTAIN_WAKEUP_LOUDNESS)
+            {
+                self.investigate(mob, origin);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Noise;
+    use crate::{ai::BrainState, test_util::test_world, ExternalEntity, Location};
+    use calx::Dir6;
+
+    #[test]
+    fn test_noise_wakes_sleepers() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let origin = world.location(player).unwrap();
+        let near = Dir6::iter()
+            .map(|&dir| origin.jump(&world, dir))
+            .find(|&loc| world.can_enter(player, loc))
+            .unwrap();
+        // Just past the reach of an explosion.
+        let far = Location {
+            x: origin.x + Noise::Explosion.intensity() as i16 + 1,
+            ..origin
+        };
+
+        let near = world.spawn(&ExternalEntity::from_name("rat").unwrap(), near);
+        let far = world.spawn(&ExternalEntity::from_name("rat").unwrap(), far);
+        world.set_brain_state(near, BrainState::Asleep);
+        world.set_brain_state(far, BrainState::Asleep);
+
+        // Right next to an explosion is loud enough to always wake up.
+        world.make_noise(origin, Noise::Explosion, None);
+        assert_eq!(
+            world.brain_state(near),
+            Some(BrainState::Investigating(origin))
+        );
+        assert_eq!(world.brain_state(far), Some(BrainState::Asleep));
+    }
+}
@@ -54,6 +54,15 @@ impl World {
         }
     }
 
+    /// Return the entity that contains entity.
+    pub fn entity_parent(&self, e: Entity) -> Option<Entity> {
+        if let Some(Place::In(parent, _)) = self.spatial.get(e) {
+            Some(parent)
+        } else {
+            None
+        }
+    }
+
     pub(crate) fn set_entity_location(&mut self, e: Entity, loc: Location) {
         self.spatial.insert_at(e, loc);
     }
@@ -1,22 +1,32 @@
 //! Data for generating game entities.
 
 use crate::{
-    ai::{Brain, ShoutType},
+    ai::{Alignment, Brain, ShoutType},
     desc::{Desc, Icon},
     item::ItemType,
-    item::{Item, Stacking},
+    item::{Consumption, Container, Item, Stacking, BAG_CAPACITY},
     sector::Biome,
     stats::{Health, Intrinsic, Stats, StatsComponent, Statuses},
+    trap::{Trap, TrapKind},
     world::Loadout,
-    Anim, Distribution, ExternalEntity, Rng,
+    Ability, Anim, Distribution, ExternalEntity, Rng,
 };
 use lazy_static::lazy_static;
-use serde;
+use rand::seq::SliceRandom;
+use rand::Rng as _;
+use serde::{
+    self,
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer as _,
+};
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 pub trait Spec: Distribution<ExternalEntity> + Sync + Send {
     /// How rare is this spec?
@@ -35,6 +45,12 @@ pub trait Spec: Distribution<ExternalEntity> + Sync + Send {
 
     fn name(&self) -> &str;
 
+    /// What does this look like?
+    fn icon(&self) -> Icon;
+
+    /// Can this be generated inside containers?
+    fn is_loot(&self) -> bool { false }
+
     /// Return base id of entity without pluralization
     fn id(&self) -> &str {
         let name = self.name();
@@ -48,20 +64,33 @@ pub trait Spec: Distribution<ExternalEntity> + Sync + Send {
 
 const EVERYWHERE: u64 = 0xffff_ffff_ffff_ffff;
 const DUNGEON: u64 = 1 << Biome::Dungeon as u64;
-const TEMPERATE: u64 = (1 << Biome::Grassland as u64) | (1 << Biome::Forest as u64);
-const ARID: u64 = (1 << Biome::Desert as u64) | (1 << Biome::Mountain as u64);
-const URBAN: u64 = 1 << Biome::City as u64;
 
-#[derive(Debug)]
+/// Deserialize a habitat bitmask from a list of biomes.
+fn deserialize_habitat<'de, D: serde::Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    let biomes: Vec<Biome> = Deserialize::deserialize(d)?;
+    Ok(biomes.into_iter().fold(0, |acc, b| acc | (1 << b as u64)))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct MobSpec {
     name: String,
     icon: Icon,
     depth: i32,
     rarity: f32,
+    #[serde(deserialize_with = "deserialize_habitat")]
     habitat: u64,
     power: i32,
+    ranged_range: u32,
+    ranged_power: i32,
     intrinsics: Vec<Intrinsic>,
+    abilities: Vec<Ability>,
     shout: ShoutType,
+    alignment: Alignment,
+    /// Number of random items the creature carries.
+    loot: u32,
+    /// Gold pieces the creature carries.
+    gold: u32,
 }
 
 impl Default for MobSpec {
@@ -73,26 +102,42 @@ impl Default for MobSpec {
             rarity: 1.0,
             habitat: EVERYWHERE,
             power: 0,
+            ranged_range: 0,
+            ranged_power: 0,
             intrinsics: Vec::new(),
+            abilities: Vec::new(),
             shout: ShoutType::Silent,
+            alignment: Alignment::Enemy,
+            loot: 0,
+            gold: 0,
         }
     }
 }
 
 impl Distribution<ExternalEntity> for MobSpec {
     fn sample(&self, _: &mut Rng) -> ExternalEntity {
-        ExternalEntity::new(
-            Loadout::default()
-                .c(StatsComponent::new(Stats::new(
-                    self.power,
-                    &self.intrinsics,
-                )))
-                .c(Desc::new(&self.name, self.icon))
-                .c(Brain::enemy().shout(self.shout))
-                .c(Anim::default())
-                .c(Health::default())
-                .c(Statuses::default()),
-        )
+        let mut loadout = Loadout::default()
+            .c(StatsComponent::new(
+                Stats::new(self.power, &self.intrinsics)
+                    .ranged_range(self.ranged_range)
+                    .ranged_power(self.ranged_power),
+            ))
+            .c(Desc::new(&self.name, self.icon))
+            .c(Brain::enemy().shout(self.shout).alignment(self.alignment))
+            .c(Anim::default())
+            .c(Health::default())
+            .c(Statuses::default());
+        if !self.abilities.is_empty() {
+            loadout = loadout.c(self.abilities.clone());
+        }
+        if self.loot > 0 || self.gold > 0 {
+            loadout = loadout.c(Container {
+                capacity: BAG_CAPACITY,
+                loot: self.loot,
+                gold: self.gold,
+            });
+        }
+        ExternalEntity::new(loadout)
     }
 }
 
@@ -101,22 +146,41 @@ impl Spec for MobSpec {
     fn min_depth(&self) -> i32 { self.depth }
     fn habitat(&self) -> u64 { self.habitat }
     fn name(&self) -> &str { &self.name }
+    fn icon(&self) -> Icon { self.icon }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ItemSpec {
     name: String,
     icon: Icon,
     depth: i32,
     rarity: f32,
+    #[serde(deserialize_with = "deserialize_habitat")]
     habitat: u64,
     item_type: ItemType,
+    consumption: Consumption,
     power: i32,
     armor: i32,
     attack: i32,
     defense: i32,
+    ranged_range: u32,
+    ranged_power: i32,
+    block: i32,
     intrinsics: Vec<Intrinsic>,
     stacks: bool,
+    /// Largest number of items in a generated stack.
+    count: u32,
+    /// Value in gold, derived from power and rarity when left at zero.
+    price: u32,
+    /// Number of bag slots, items with capacity are containers.
+    capacity: u32,
+    /// Number of random items a generated container holds.
+    loot: u32,
+    /// Probability for a spawned item to be cursed.
+    curse_chance: f32,
+    min_level: i32,
+    min_power: i32,
 }
 
 impl Default for ItemSpec {
@@ -129,388 +193,337 @@ impl Default for ItemSpec {
             // As a rule, you don't find items laying around in the wilderness.
             habitat: DUNGEON,
             item_type: ItemType::MeleeWeapon,
+            consumption: Consumption::SingleUse,
             power: 0,
             armor: 0,
             attack: 0,
             defense: 0,
+            ranged_range: 0,
+            ranged_power: 0,
+            block: 0,
             intrinsics: Vec::new(),
             stacks: false,
+            count: 1,
+            price: 0,
+            capacity: 0,
+            loot: 0,
+            curse_chance: 0.0,
+            min_level: 0,
+            min_power: 0,
         }
     }
 }
 
 impl Distribution<ExternalEntity> for ItemSpec {
-    fn sample(&self, _: &mut Rng) -> ExternalEntity {
+    fn sample(&self, rng: &mut Rng) -> ExternalEntity {
         let mut loadout = Loadout::default()
             .c(Desc::new(&self.name, self.icon))
             .c(StatsComponent::new(
                 Stats::new(self.power, &self.intrinsics)
                     .armor(self.armor)
                     .attack(self.attack)
-                    .defense(self.defense),
+                    .defense(self.defense)
+                    .ranged_range(self.ranged_range)
+                    .ranged_power(self.ranged_power)
+                    .block(self.block),
             ))
             .c(Item {
                 item_type: self.item_type,
-                charges: 1,
+                charges: self.consumption.max_charges(),
+                consumption: self.consumption,
+                cursed: rng.gen::<f32>() < self.curse_chance,
+                curse_known: false,
+                min_level: self.min_level,
+                min_power: self.min_power,
+                price: self.price(),
             });
         if self.stacks {
-            loadout = loadout.c(Stacking::default());
+            loadout = loadout.c(Stacking {
+                count: rng.gen_range(1..=self.count.max(1)),
+            });
+        }
+        if self.capacity > 0 {
+            loadout = loadout.c(Container {
+                capacity: self.capacity,
+                loot: self.loot,
+                gold: 0,
+            });
         }
         ExternalEntity::new(loadout)
     }
 }
 
+impl ItemSpec {
+    /// Price of one item.
+    ///
+    /// Unless the spec gives a price, more powerful and rarer items are worth more.
+    fn price(&self) -> u32 {
+        const BASE_PRICE: f32 = 10.0;
+        // Items this common go for the base price, rarer items cost more.
+        const COMMON_RARITY: f32 = 10.0;
+
+        if self.price > 0 {
+            return self.price;
+        }
+        let power =
+            self.power + self.armor + self.attack + self.defense + self.ranged_power + self.block;
+        // Rarity is the inverse of spawn probability.
+        let scarcity = (self.rarity.max(1.0) / COMMON_RARITY).sqrt();
+        (BASE_PRICE * (1 + power.max(0)) as f32 * scarcity) as u32
+    }
+}
+
 impl Spec for ItemSpec {
     fn rarity(&self) -> f32 { self.rarity }
     fn min_depth(&self) -> i32 { self.depth }
     fn habitat(&self) -> u64 { self.habitat }
     fn name(&self) -> &str { &self.name }
+    fn icon(&self) -> Icon { self.icon }
+    // No containers inside containers, and nothing that goes off when you step on it.
+    fn is_loot(&self) -> bool {
+        self.capacity == 0 && !matches!(self.item_type, ItemType::Instant(_))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrapSpec {
+    name: String,
+    depth: i32,
+    rarity: f32,
+    #[serde(deserialize_with = "deserialize_habitat")]
+    habitat: u64,
+    /// Kinds of trap to pick from when spawning.
+    kinds: Vec<TrapKind>,
+}
+
+impl Default for TrapSpec {
+    fn default() -> Self {
+        TrapSpec {
+            name: "N/A".into(),
+            depth: 0,
+            rarity: 1.0,
+            habitat: DUNGEON,
+            kinds: TrapKind::ALL.to_vec(),
+        }
+    }
+}
+
+impl Distribution<ExternalEntity> for TrapSpec {
+    fn sample(&self, rng: &mut Rng) -> ExternalEntity {
+        let kind = *self.kinds.choose(rng).unwrap_or(&TrapKind::Alarm);
+        let loadout = Loadout::default()
+            .c(Desc::new(kind.name(), Icon::Trap))
+            .c(Trap { kind, hidden: true });
+        ExternalEntity::new(loadout)
+    }
+}
+
+impl Spec for TrapSpec {
+    fn rarity(&self) -> f32 { self.rarity }
+    fn min_depth(&self) -> i32 { self.depth }
+    fn habitat(&self) -> u64 { self.habitat }
+    fn name(&self) -> &str { &self.name }
+    fn icon(&self) -> Icon { Icon::Trap }
+}
+
+/// Spec data files that are compiled into the game.
+const EMBEDDED_MOBS: &str = include_str!("../assets/specs/mobs.ron");
+const EMBEDDED_ITEMS: &str = include_str!("../assets/specs/items.ron");
+const EMBEDDED_TRAPS: &str = include_str!("../assets/specs/traps.ron");
+
+const MOBS_FILE: &str = "mobs.ron";
+const ITEMS_FILE: &str = "items.ron";
+const TRAPS_FILE: &str = "traps.ron";
+
+type SpecTable = BTreeMap<EntitySpawn, Arc<dyn Spec>>;
+
+lazy_static! {
+    static ref SPECS: RwLock<SpecTable> = RwLock::new(
+        parse_specs(EMBEDDED_MOBS, EMBEDDED_ITEMS, EMBEDDED_TRAPS)
+            .unwrap_or_else(|e| panic!("Invalid built-in spec data: {}", e))
+    );
 }
 
-macro_rules! specs {
-    {$($item:expr,)+}
-    =>
-    {
-        lazy_static! {
-            pub static ref SPECS: BTreeMap<EntitySpawn, Arc<dyn Spec>> = {
-                let mut ret: BTreeMap<EntitySpawn, Arc<dyn Spec>> = BTreeMap::new();
-                $(ret.insert(EntitySpawn($item.id().to_string()), Arc::new($item));)+
-                ret
-            };
+pub fn iter_specs() -> impl Iterator<Item = Arc<dyn Spec + 'static>> {
+    let specs: Vec<Arc<dyn Spec>> = SPECS.read().unwrap().values().cloned().collect();
+    specs.into_iter()
+}
+
+/// Replace the spec database with data files from a directory.
+///
+/// The directory may contain `mobs.ron`, `items.ron` and `traps.ron`, the built-in data is used
+/// for any file that is missing. The current database is left untouched if the new data has errors.
+pub fn load_specs(dir: impl AsRef<Path>) -> Result<(), SpecError> {
+    let read = |file: &str, embedded: &str| match fs::read_to_string(dir.as_ref().join(file)) {
+        Ok(data) => Ok(data),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(embedded.to_string()),
+        Err(e) => Err(SpecError::new(file, e)),
+    };
+    let mobs = read(MOBS_FILE, EMBEDDED_MOBS)?;
+    let items = read(ITEMS_FILE, EMBEDDED_ITEMS)?;
+    let traps = read(TRAPS_FILE, EMBEDDED_TRAPS)?;
+
+    let specs = parse_specs(&mobs, &items, &traps)?;
+    *SPECS.write().unwrap() = specs;
+    Ok(())
+}
+
+/// Error in spec data, names the file and the entry where the problem is.
+#[derive(Debug)]
+pub struct SpecError {
+    file: String,
+    line: Option<usize>,
+    entry: Option<String>,
+    message: String,
+}
+
+impl SpecError {
+    fn new(file: &str, message: impl fmt::Display) -> SpecError {
+        SpecError {
+            file: file.to_string(),
+            line: None,
+            entry: None,
+            message: message.to_string(),
+        }
+    }
+
+    fn at_line(mut self, line: usize) -> SpecError {
+        self.line = Some(line);
+        self
+    }
+
+    fn in_entry(mut self, entry: &str) -> SpecError {
+        self.entry = Some(entry.to_string());
+        self
+    }
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(ref entry) = self.entry {
+            write!(f, ": entry \"{}\"", entry)?;
         }
+        write!(f, ": {}", self.message)
     }
 }
 
-pub fn iter_specs() -> impl Iterator<Item = Arc<dyn Spec + 'static>> { SPECS.values().cloned() }
-
-use self::Intrinsic::*;
-use self::ShoutType::*;
-use crate::effect::Ability::*;
-use crate::Icon as I;
-use crate::ItemType::*;
-
-specs! {
-    // Mobs
-    MobSpec {
-        name: "player".into(),
-        icon: I::Player,
-        rarity: 0.0,
-        power: 10,
-        intrinsics: vec![Hands],
-        shout: Shout,
-        ..d()
-    },
-    MobSpec {
-        name: "dreg".into(),
-        icon: I::Dreg,
-        habitat: DUNGEON,
-        power: 2,
-        intrinsics: vec![Hands],
-        shout: Shout,
-        ..d()
-    },
-    MobSpec {
-        name: "snake".into(),
-        icon: I::Snake,
-        habitat: DUNGEON | TEMPERATE | ARID | URBAN,
-        power: 1,
-        shout: Hiss,
-        ..d()
-    },
-    MobSpec {
-        name: "ooze".into(),
-        icon: I::Ooze,
-        depth: 1,
-        habitat: DUNGEON,
-        power: 3,
-        shout: Gurgle,
-        ..d()
-    },
-    MobSpec {
-        name: "bug".into(),
-        icon: I::Bug,
-        depth: 2,
-        rarity: 10.0,
-        power: 2,
-        ..d()
-    },
-    MobSpec {
-        name: "octopus|octopi".into(),
-        icon: I::Octopus,
-        depth: 2,
-        habitat: DUNGEON | TEMPERATE,
-        power: 5,
-        intrinsics: vec![Hands],
-        ..d()
-    },
-    MobSpec {
-        name: "ogre".into(),
-        icon: I::Ogre,
-        depth: 5,
-        rarity: 4.0,
-        habitat: DUNGEON | ARID,
-        power: 7,
-        intrinsics: vec![Hands],
-        shout: Shout,
-        ..d()
-    },
-    MobSpec {
-        name: "wraith".into(),
-        icon: I::Wraith,
-        depth: 6,
-        habitat: DUNGEON,
-        rarity: 6.0,
-        power: 10,
-        intrinsics: vec![Hands],
-        ..d()
-    },
-    MobSpec {
-        name: "efreeti|efreet".into(),
-        icon: I::Efreet,
-        depth: 7,
-        habitat: DUNGEON,
-        rarity: 8.0,
-        power: 14,
-        intrinsics: vec![Hands],
-        ..d()
-    },
-    MobSpec {
-        name: "serpent".into(),
-        icon: I::Serpent,
-        depth: 9,
-        habitat: DUNGEON,
-        rarity: 10.0,
-        power: 20,
-        shout: Hiss,
-        ..d()
-    },
-    MobSpec {
-        name: "bear".into(),
-        icon: I::Bear,
-        depth: 2,
-        habitat: TEMPERATE,
-        power: 7,
-        shout: Roar,
-        ..d()
-    },
-    MobSpec {
-        name: "spider".into(),
-        icon: I::Spider,
-        depth: 4,
-        habitat: DUNGEON | ARID,
-        power: 20,
-        rarity: 10.0,
-        shout: Hiss,
-        ..d()
-    },
-    MobSpec {
-        name: "totem guardian".into(),
-        icon: I::TotemGuardian,
-        depth: 4,
-        habitat: DUNGEON,
-        rarity: 5.0,
-        power: 5,
-        intrinsics: vec![Hands],
-        ..d()
-    },
-    MobSpec {
-        name: "bat".into(),
-        icon: I::Bat,
-        habitat: DUNGEON | TEMPERATE | URBAN | ARID,
-        power: 1,
-        intrinsics: vec![Hyperactive],
-        ..d()
-    },
-    MobSpec {
-        name: "centaur".into(),
-        icon: I::Centaur,
-        habitat: DUNGEON | TEMPERATE | ARID,
-        depth: 4,
-        power: 5,
-        rarity: 3.0,
-        shout: Shout,
-        intrinsics: vec![Hands],
-        ..d()
-    },
-    MobSpec {
-        name: "armored construct".into(),
-        icon: I::ArmorConstruct,
-        habitat: DUNGEON,
-        depth: 6,
-        power: 8,
-        rarity: 5.0,
-        intrinsics: vec![Hands],
-        ..d()
-    },
-    MobSpec {
-        name: "curse skull".into(),
-        icon: I::CurseSkull,
-        habitat: DUNGEON,
-        depth: 6,
-        power: 8,
-        rarity: 5.0,
-        shout: Shout,
-        ..d()
-    },
-    MobSpec {
-        name: "gelatinous cube".into(),
-        icon: I::GelatinousCube,
-        habitat: DUNGEON,
-        depth: 3,
-        power: 5,
-        rarity: 3.0,
-        shout: Gurgle,
-        ..d()
-    },
-    MobSpec {
-        name: "crocodile".into(),
-        icon: I::Crocodile,
-        habitat: TEMPERATE | ARID,
-        depth: 3,
-        power: 5,
-        rarity: 2.0,
-        shout: Roar,
-        ..d()
-    },
-    MobSpec {
-        name: "wisp".into(),
-        icon: I::Wisp,
-        habitat: DUNGEON,
-        depth: 5,
-        power: 5,
-        rarity: 10.0,
-        ..d()
-    },
-    MobSpec {
-        name: "vortex|vortices".into(),
-        icon: I::Vortex,
-        habitat: DUNGEON,
-        depth: 8,
-        power: 10,
-        rarity: 10.0,
-        ..d()
-    },
-    MobSpec {
-        name: "moloch".into(),
-        icon: I::Moloch,
-        habitat: DUNGEON,
-        depth: 10,
-        power: 50,
-        rarity: 20.0,
-        ..d()
-    },
-    MobSpec {
-        name: "lizardman|lizardmen".into(),
-        icon: I::Lizardman,
-        habitat: TEMPERATE | ARID,
-        power: 2,
-        rarity: 2.0,
-        shout: Shout,
-        intrinsics: vec![Hands],
-        ..d()
-    },
-    MobSpec {
-        name: "centipede".into(),
-        icon: I::Centipede,
-        habitat: DUNGEON,
-        depth: 8,
-        power: 10,
-        rarity: 8.0,
-        ..d()
-    },
-    MobSpec {
-        name: "floating eye".into(),
-        icon: I::FloatingEye,
-        habitat: DUNGEON,
-        power: 2,
-        ..d()
-    },
-    MobSpec {
-        name: "eye horror".into(),
-        icon: I::EyeHorror,
-        habitat: DUNGEON,
-        depth: 6,
-        power: 8,
-        rarity: 3.0,
-        intrinsics: vec![Deathsplosion],
-        ..d()
-    },
-    MobSpec {
-        name: "dog".into(),
-        icon: I::Dog,
-        habitat: DUNGEON,
-        power: 3,
-        rarity: 10.0,
-        ..d()
-    },
-    MobSpec {
-        name: "cat".into(),
-        icon: I::Cat,
-        habitat: DUNGEON,
-        power: 3,
-        rarity: 10.0,
-        ..d()
-    },
-    MobSpec {
-        name: "rat".into(),
-        icon: I::Rat,
-        habitat: DUNGEON | TEMPERATE | ARID,
-        power: 1,
-        ..d()
-    },
-
-
-    // Items
-    ItemSpec {
-        name: "sword".into(),
-        icon: I::Sword,
-        item_type: MeleeWeapon,
-        rarity: 10.0,
-        attack: 6,
-        ..d()
-    },
-    ItemSpec {
-        name: "helmet".into(),
-        icon: I::Helmet,
-        item_type: Helmet,
-        rarity: 10.0,
-        armor: 2,
-        ..d()
-    },
-    ItemSpec {
-        name: "armor".into(),
-        icon: I::Armor,
-        item_type: Armor,
-        rarity: 10.0,
-        armor: 5,
-        ..d()
-    },
-    ItemSpec {
-        name: "wand of fireball|wands of fireball".into(),
-        icon: I::Wand1,
-        power: 5,
-        item_type: TargetedUsable(Fireball),
-        rarity: 10.0,
-        depth: 3,
-        ..d()
-    },
-    ItemSpec {
-        name: "wand of confusion|wands of confusion".into(),
-        icon: I::Wand2,
-        power: 5,
-        item_type: TargetedUsable(Confuse),
-        rarity: 10.0,
-        armor: 5,
-        ..d()
-    },
-    ItemSpec {
-        name: "scroll of lightning|scrolls of lightning".into(),
-        icon: I::Scroll1,
-        power: 1,
-        item_type: UntargetedUsable(LightningBolt),
-        stacks: true,
-        ..d()
-    },
+impl Error for SpecError {}
+
+/// Build the spec table from the text of the spec data files.
+fn parse_specs(mobs: &str, items: &str, traps: &str) -> Result<SpecTable, SpecError> {
+    let mut ret = SpecTable::new();
+
+    for spec in parse_file::<MobSpec>(MOBS_FILE, mobs)? {
+        insert_spec(&mut ret, MOBS_FILE, Arc::new(spec))?;
+    }
+    for spec in parse_file::<ItemSpec>(ITEMS_FILE, items)? {
+        insert_spec(&mut ret, ITEMS_FILE, Arc::new(spec))?;
+    }
+    for spec in parse_file::<TrapSpec>(TRAPS_FILE, traps)? {
+        if spec.kinds.is_empty() {
+            return Err(SpecError::new(TRAPS_FILE, "No trap kinds").in_entry(&spec.name));
+        }
+        insert_spec(&mut ret, TRAPS_FILE, Arc::new(spec))?;
+    }
+
+    if !ret.contains_key(&*PLAYER_SPAWN) {
+        return Err(SpecError::new(
+            MOBS_FILE,
+            format!("No entry for \"{}\"", *PLAYER_SPAWN),
+        ));
+    }
+
+    Ok(ret)
+}
+
+/// Parse a spec file containing a list of entries.
+///
+/// Errors point out the offending entry by name when it can be found.
+fn parse_file<T: for<'de> Deserialize<'de>>(file: &str, data: &str) -> Result<Vec<T>, SpecError> {
+    let mut ret = Vec::new();
+    if let Err((line, e)) = read_entries(data, &mut ret) {
+        let mut err = SpecError::new(file, e.code).at_line(line);
+
+        // The entries before the failed one were read fine, so the failed entry's index is the
+        // number of entries read. Get the name from a second pass that only looks at names.
+        let mut names: Vec<EntryName> = Vec::new();
+        let _ = read_entries(data, &mut names);
+        match names.get(ret.len()) {
+            Some(EntryName { name }) if !name.is_empty() => err = err.in_entry(name),
+            _ => {}
+        }
+        return Err(err);
+    }
+    Ok(ret)
+}
+
+/// Read a RON list into a vector, keeping the elements read before any error.
+///
+/// Errors come with the line where reading stopped.
+fn read_entries<'de, T: Deserialize<'de>>(
+    data: &'de str,
+    ret: &mut Vec<T>,
+) -> Result<(), (usize, ron::Error)> {
+    struct Entries<'a, T>(&'a mut Vec<T>);
+
+    impl<'de, 'a, T: Deserialize<'de>> Visitor<'de> for Entries<'a, T> {
+        type Value = ();
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "a list of entries")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+            while let Some(entry) = seq.next_element()? {
+                self.0.push(entry);
+            }
+            Ok(())
+        }
+    }
+
+    let mut de = ron::de::Deserializer::from_str(data).map_err(|e| (e.position.line, e))?;
+    de.deserialize_seq(Entries(ret))
+        .and_then(|_| de.end())
+        .map_err(|e| {
+            // Errors from serde itself, like unknown fields, don't know their position, so look
+            // at how far the deserializer got instead.
+            let offset = data.len() - de.remainder().len();
+            (data[..offset].matches('\n').count() + 1, e)
+        })
+}
+
+/// Just the name field of a spec entry.
+#[derive(Deserialize)]
+struct EntryName {
+    #[serde(default)]
+    name: String,
+}
+
+/// Validate spec and add it to table.
+fn insert_spec(table: &mut SpecTable, file: &str, spec: Arc<dyn Spec>) -> Result<(), SpecError> {
+    let err = |msg: &str| Err(SpecError::new(file, msg).in_entry(spec.name()));
+
+    if spec.id().is_empty() {
+        return err("Empty name");
+    }
+    if spec.name().matches('|').count() > 1 || spec.name().ends_with('|') {
+        return err("Malformed plural name, use \"singular|plural\"");
+    }
+    if spec.rarity().is_nan() || spec.rarity() < 0.0 {
+        return err("Rarity must be zero or positive");
+    }
+
+    let key = EntitySpawn(spec.id().to_string());
+    if table.contains_key(&key) {
+        return err("Duplicate entry");
+    }
+    table.insert(key, spec);
+    Ok(())
 }
 
 /// String that's guaranteed to describe an entity spawn.
@@ -540,7 +553,11 @@ impl FromStr for EntitySpawn {
     type Err = SpawnError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !SPECS.contains_key(&EntitySpawn(s.to_string())) {
+        if !SPECS
+            .read()
+            .unwrap()
+            .contains_key(&EntitySpawn(s.to_string()))
+        {
             Err(SpawnError(s.to_string()))
         } else {
             Ok(EntitySpawn(s.to_string()))
@@ -564,16 +581,14 @@ impl<'a> serde::Deserialize<'a> for EntitySpawn {
 impl Distribution<ExternalEntity> for EntitySpawn {
     fn sample(&self, rng: &mut Rng) -> ExternalEntity {
         SPECS
+            .read()
+            .unwrap()
             .get(self)
             .unwrap_or_else(|| panic!("EntitySpawn {:?} not found in spec database", self))
             .sample(rng)
     }
 }
 
-// Helpers for data conciseness.
-
-fn d<T: Default>() -> T { Default::default() }
-
 #[cfg(test)]
 mod test {
     #[test]
@@ -598,4 +613,85 @@ mod test {
         // Names not in database don't.
         assert!(ron::de::from_str::<EntitySpawn>(&"\"tyop txet\"".to_string()).is_err());
     }
+
+    #[test]
+    fn test_spec_errors() {
+        use super::{parse_specs, EMBEDDED_ITEMS, EMBEDDED_MOBS, EMBEDDED_TRAPS};
+
+        assert!(parse_specs(EMBEDDED_MOBS, EMBEDDED_ITEMS, EMBEDDED_TRAPS).is_ok());
+
+        let error = |mobs: &str| {
+            parse_specs(mobs, EMBEDDED_ITEMS, EMBEDDED_TRAPS)
+                .err()
+                .expect("Bad spec data was accepted")
+                .to_string()
+        };
+
+        // Parse errors name the entry they're in.
+        let bad_field = r#"[
+    (
+        name: "player",
+    ),
+    (
+        name: "gremlin",
+        icon: Dreg,
+        powr: 3,
+    ),
+]"#;
+        let err = error(bad_field);
+        assert!(
+            err.contains("mobs.ron") && err.contains("gremlin"),
+            "{}",
+            err
+        );
+
+        // So do validation errors.
+        let duplicate = r#"[
+    (name: "player"),
+    (name: "dreg"),
+    (name: "dreg"),
+]"#;
+        let err = error(duplicate);
+        assert!(err.contains("Duplicate") && err.contains("dreg"), "{}", err);
+
+        // Brackets in char literals don't throw off finding the entry.
+        let bad_char = r#"[
+    (
+        name: "player",
+    ),
+    (
+        name: "gremlin",
+        icon: ')',
+    ),
+    (
+        name: "dreg",
+    ),
+]"#;
+        let err = error(bad_char);
+        assert!(err.contains("gremlin"), "{}", err);
+
+        // And so do raw strings.
+        let raw_string = r##"[
+    (
+        name: "player",
+    ),
+    (
+        name: r#"gremlin (the "small" one)"#,
+        icon: Dreg,
+        powr: 3,
+    ),
+    (
+        name: "dreg",
+    ),
+]"##;
+        let err = error(raw_string);
+        assert!(
+            err.contains("mobs.ron:8") && err.contains("gremlin (the \"small\" one)"),
+            "{}",
+            err
+        );
+
+        // Player spec is required.
+        assert!(error("[]").contains("player"));
+    }
 }
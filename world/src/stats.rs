@@ -1,14 +1,34 @@
 use crate::{
-    attack_damage, effect::Damage, msg, roll, Ability, ActionOutcome, AnimState, ItemType, Slot,
-    World,
+    attack_damage,
+    effect::{Damage, Effect},
+    msg, roll,
+    sound::Noise,
+    Ability, ActionOutcome, AnimState, ItemType, Location, Slot, World,
 };
 use calx::Dir6;
 use calx_ecs::Entity;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::default::Default;
 use std::ops::Add;
 
+/// Effect of melee hits by entities with the Drain intrinsic.
+const DRAINING_TOUCH: Effect = Effect::Drain {
+    amount: 50,
+    lethal: false,
+};
+
+/// How long a poisonous melee hit keeps the target poisoned, in frames.
+const POISON_DURATION: u32 = 60;
+
+/// Experience points needed to gain a level.
+const XP_PER_LEVEL: i32 = 100;
+
+/// Cap for the block chance percentage, stacking shields can't make you invulnerable.
+const MAX_BLOCK_CHANCE: i32 = 75;
+
 /// Stats specifies static bonuses for an entity. Stats values can be added
 /// together to build composites. The Default value for Stats must be an
 /// algebraic zero element, adding it to any Stats value must leave that value
@@ -29,6 +49,8 @@ pub struct Stats {
     pub ranged_range: u32,
     /// Ranged attack power
     pub ranged_power: i32,
+    /// Percentage chance to block melee attacks
+    pub block: i32,
 
     /// Character level
     pub level: i32,
@@ -76,6 +98,7 @@ impl Stats {
             ..self
         }
     }
+    pub fn block(self, block: i32) -> Stats { Stats { block, ..self } }
 
     pub fn add_intrinsic(&mut self, intrinsic: Intrinsic) {
         self.intrinsics |= 1 << intrinsic as u32;
@@ -99,6 +122,7 @@ impl Add<Stats> for Stats {
             // type dealie.
             ranged_range: self.ranged_range + other.ranged_range,
             ranged_power: self.ranged_power + other.ranged_power,
+            block: self.block + other.block,
 
             level: self.level + other.level,
             xp: self.xp + other.xp,
@@ -138,10 +162,48 @@ pub enum Status {
     ///
     /// This gets jumped up every time after the creature acted.
     Delayed,
+    /// Takes damage over time.
+    Poisoned,
+    /// Heals quickly.
+    Regenerating,
+    /// Can't act and is easier to hit.
+    Stunned,
+    /// On fire, takes fire damage over time.
+    Burning,
+    /// Can't be seen by others.
+    Invisible,
+}
+
+impl Status {
+    /// Return whether the status is something the player should be told about.
+    ///
+    /// Internal bookkeeping statuses are not shown.
+    pub fn is_visible(self) -> bool { !matches!(self, Status::Dead | Status::Delayed) }
+
+    /// The type of damage the status deals over time, if any.
+    fn damage_type(self) -> Option<Damage> {
+        match self {
+            Status::Poisoned => Some(Damage::Poison),
+            Status::Burning => Some(Damage::Fire),
+            _ => None,
+        }
+    }
+
+    /// Stat modifiers the status applies while in effect.
+    fn stat_modifier(self) -> Stats {
+        match self {
+            Status::Stunned => Stats::default().defense(-5),
+            _ => Stats::default(),
+        }
+    }
 }
 
+/// Temporary statuses of an entity and their remaining durations in frames.
 pub type Statuses = BTreeMap<Status, u32>;
 
+/// Number of frames between the per-turn effects of statuses, same as a normal speed action.
+const STATUS_TICK_FRAMES: u32 = 12;
+
 /// Stats component in the ECS that supports caching applied modifiers for efficiency.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct StatsComponent {
@@ -170,6 +232,79 @@ pub enum Intrinsic {
     Deathsplosion,
     /// Always roaming, can't go to sleep state
     Hyperactive,
+    /// Melee hits drain experience
+    Drain,
+    /// Melee hits poison
+    Poisonous,
+    /// Takes half damage from fire
+    ResistFire,
+    /// Takes no damage from fire
+    ImmuneFire,
+    /// Takes double damage from fire
+    VulnerableFire,
+    /// Takes half damage from electricity
+    ResistElectricity,
+    /// Takes no damage from electricity
+    ImmuneElectricity,
+    /// Takes double damage from electricity
+    VulnerableElectricity,
+    /// Takes half damage from poison
+    ResistPoison,
+    /// Takes no damage from poison
+    ImmunePoison,
+    /// Takes double damage from poison
+    VulnerablePoison,
+    /// Never flees from a fight
+    Mindless,
+    /// Stays put minding a shop unless provoked
+    Shopkeeper,
+}
+
+/// How an entity reacts to a specific type of damage.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Susceptibility {
+    Immune,
+    Resistant,
+    Normal,
+    Vulnerable,
+}
+
+impl Susceptibility {
+    /// Apply the susceptibility to an amount of damage.
+    pub fn apply(self, amount: i32) -> i32 {
+        match self {
+            Susceptibility::Immune => 0,
+            Susceptibility::Resistant => amount / 2,
+            Susceptibility::Normal => amount,
+            Susceptibility::Vulnerable => amount * 2,
+        }
+    }
+}
+
+impl Damage {
+    /// Return the (resist, immune, vulnerable) intrinsics for the damage type.
+    ///
+    /// Damage types with no intrinsics always do normal damage.
+    fn resistance_intrinsics(self) -> Option<(Intrinsic, Intrinsic, Intrinsic)> {
+        use Intrinsic::*;
+        match self {
+            Damage::Fire => Some((ResistFire, ImmuneFire, VulnerableFire)),
+            Damage::Electricity => {
+                Some((ResistElectricity, ImmuneElectricity, VulnerableElectricity))
+            }
+            Damage::Poison => Some((ResistPoison, ImmunePoison, VulnerablePoison)),
+            Damage::Physical | Damage::Drain => None,
+        }
+    }
+
+    /// Return the status a hit of the damage type leaves on the target and its duration.
+    pub(crate) fn aftereffect(self) -> Option<(Status, u32)> {
+        match self {
+            Damage::Fire => Some((Status::Burning, 36)),
+            Damage::Electricity => Some((Status::Stunned, 12)),
+            _ => None,
+        }
+    }
 }
 
 impl World {
@@ -228,6 +363,43 @@ impl World {
             .map_or(false, |s| s.contains_key(&status))
     }
 
+    /// Return the remaining duration of a status in frames, or None if the entity doesn't have
+    /// the status.
+    pub fn status_duration(&self, e: Entity, status: Status) -> Option<u32> {
+        self.ecs().status.get(e)?.get(&status).cloned()
+    }
+
+    /// Return the statuses currently affecting an entity along with their remaining durations in
+    /// frames.
+    pub fn statuses(&self, e: Entity) -> Vec<(Status, u32)> {
+        self.ecs()
+            .status
+            .get(e)
+            .map_or_else(Vec::new, |s| s.iter().map(|(&k, &d)| (k, d)).collect())
+    }
+
+    /// Return how the entity is affected by a type of damage.
+    ///
+    /// Immunity beats everything else, resistance and vulnerability cancel each other out.
+    pub fn susceptibility(&self, e: Entity, damage_type: Damage) -> Susceptibility {
+        let (resist, immune, vulnerable) = match damage_type.resistance_intrinsics() {
+            Some(intrinsics) => intrinsics,
+            None => return Susceptibility::Normal,
+        };
+
+        if self.has_intrinsic(e, immune) {
+            return Susceptibility::Immune;
+        }
+        match (
+            self.has_intrinsic(e, resist),
+            self.has_intrinsic(e, vulnerable),
+        ) {
+            (true, false) => Susceptibility::Resistant,
+            (false, true) => Susceptibility::Vulnerable,
+            _ => Susceptibility::Normal,
+        }
+    }
+
     pub fn has_ability(&self, e: Entity, ability: Ability) -> bool {
         self.list_abilities(e).into_iter().any(|x| x == ability)
     }
@@ -245,10 +417,21 @@ impl World {
                 ItemType::Instant(ability) => {
                     return vec![ability];
                 }
+                ItemType::Food(_) => {
+                    return vec![Ability::Eat];
+                }
+                ItemType::Spellbook(_) => {
+                    return vec![Ability::Study];
+                }
                 _ => {}
             }
         }
 
+        // Check for innate abilities.
+        if let Some(abilities) = self.ecs().ability.get(e) {
+            return abilities.clone();
+        }
+
         // Entity has no abilites.
         Vec::new()
     }
@@ -260,10 +443,14 @@ impl World {
         damage_type: Damage,
         source: Option<Entity>,
     ) {
+        // Sources can get caught in their own area effects.
+        let source = source.filter(|&attacker| attacker != e);
+
         if let Some(attacker) = source {
             self.notify_attacked_by(e, attacker);
         }
 
+        let amount = self.soak_damage(e, amount, damage_type);
         let max_hp = self.max_hp(e);
 
         let mut hurt = false;
@@ -293,7 +480,8 @@ impl World {
                 self.gain_kill_xp(attacker, e);
             }
 
-            if let Some(loc) = self.location(e) {
+            let loc = self.location(e);
+            if let Some(loc) = loc {
                 if self.player_sees(loc) {
                     // TODO: message templating
                     msg!(
@@ -302,16 +490,91 @@ impl World {
                             Damage::Physical => "die[s]",
                             Damage::Fire => "burn[s] to ash",
                             Damage::Electricity => "[is] electrocuted",
+                            Damage::Drain => "wither[s] away",
+                            Damage::Poison => "succumb[s] to poison",
                         };
                         self.subject(e)
                     );
                 }
                 self.spawn_fx(loc, AnimState::Gib);
+
+                if self.is_mob(e) && self.is_container(e) {
+                    // Creatures carrying loot drop it when they die.
+                    for (_, item) in self.entities_in_bag(e) {
+                        self.place_entity(item, loc);
+                    }
+                }
             }
             self.kill_entity(e);
+
+            if let Some(loc) = loc {
+                self.death_triggers(e, loc, source);
+            }
         }
     }
 
+    /// Run effects that happen when an entity dies.
+    ///
+    /// Called after the entity has been removed from the map, so it won't get caught in its own
+    /// death effects.
+    fn death_triggers(&mut self, e: Entity, loc: Location, killer: Option<Entity>) {
+        if self.has_intrinsic(e, Intrinsic::Deathsplosion) {
+            const EXPLOSION_RADIUS: u32 = 1;
+
+            if self.player_sees(loc) {
+                msg!("[One] explode[s]!"; self.subject(e));
+            }
+
+            let volume = self.sphere_volume(loc, EXPLOSION_RADIUS);
+            for &pt in &volume.0 {
+                self.spawn_fx(pt, AnimState::Explosion);
+            }
+            self.make_noise(loc, Noise::Explosion, killer);
+
+            // Other exploding mobs killed by the blast go off recursively before this call
+            // returns. The volume is always traversed in the same order, so chain reactions play
+            // out the same way every time.
+            //
+            // The killer gets the blame for the explosion.
+            let effect = Effect::Hit {
+                amount: self.power(e).max(0) as u32,
+                damage: Damage::Fire,
+            };
+            self.apply_effect(&effect, &volume, killer);
+        }
+    }
+
+    /// Reduce incoming damage by armor and resistances.
+    fn soak_damage(&mut self, e: Entity, amount: i32, damage_type: Damage) -> i32 {
+        if amount <= 0 {
+            return amount;
+        }
+
+        if damage_type == Damage::Physical {
+            // Armor only helps against getting physically hit.
+            let armor = self.stats(e).armor.max(0);
+            return amount * 10 / (10 + armor);
+        }
+
+        let susceptibility = self.susceptibility(e, damage_type);
+        if susceptibility != Susceptibility::Normal && self.player_notices(e) {
+            let name = damage_type.name();
+            match susceptibility {
+                Susceptibility::Immune => {
+                    msg!("[One] [is] unharmed by the {}.", name; self.subject(e));
+                }
+                Susceptibility::Resistant => {
+                    msg!("[One] resist[s] the {}.", name; self.subject(e));
+                }
+                Susceptibility::Vulnerable => {
+                    msg!("The {} sears [one]!", name; self.subject(e));
+                }
+                Susceptibility::Normal => {}
+            }
+        }
+        susceptibility.apply(amount)
+    }
+
     /// Do a single step of natural regeneration for a creature.
     ///
     /// Return amount of health gained, or None if at full health.
@@ -333,7 +596,13 @@ impl World {
         if duration == 0 {
             return;
         }
+        if let Some(damage_type) = status.damage_type() {
+            if self.susceptibility(e, damage_type) == Susceptibility::Immune {
+                return;
+            }
+        }
 
+        let mut is_new = false;
         if let Some(statuses) = self.ecs_mut().status.get_mut(e) {
             if let Some(current_duration) = statuses.get(&status).cloned() {
                 if duration > current_duration {
@@ -341,27 +610,149 @@ impl World {
                     statuses.insert(status, duration);
                 }
             } else {
-                // TODO: Special stuff when status first goes into effect goes here
                 statuses.insert(status, duration);
+                is_new = true;
             }
         }
+
+        if is_new {
+            self.status_started(e, status);
+        }
     }
 
-    pub(crate) fn tick_statuses(&mut self, e: Entity) {
+    /// End a status before its duration runs out.
+    pub(crate) fn remove_status(&mut self, e: Entity, status: Status) {
         if let Some(statuses) = self.ecs_mut().status.get_mut(e) {
-            let mut remove = Vec::new();
+            if statuses.remove(&status).is_some() {
+                self.status_ended(e, status);
+            }
+        }
+    }
+
+    pub(crate) fn tick_statuses(&mut self, e: Entity) {
+        let mut ticked = Vec::new();
+        let mut expired = Vec::new();
 
+        if let Some(statuses) = self.ecs_mut().status.get_mut(e) {
             for (k, d) in statuses.iter_mut() {
                 *d -= 1;
                 if *d == 0 {
-                    remove.push(*k);
+                    expired.push(*k);
+                } else if *d % STATUS_TICK_FRAMES == 0 {
+                    ticked.push(*k);
                 }
             }
 
-            // TODO: Special stuff when status goes out of effect for dropped statuses.
-            for k in remove.into_iter() {
-                statuses.remove(&k);
+            for k in &expired {
+                statuses.remove(k);
+            }
+        }
+
+        for status in ticked {
+            self.status_tick(e, status);
+        }
+
+        for status in expired {
+            self.status_ended(e, status);
+        }
+    }
+
+    /// Return whether the player is in a position to notice things happening to an entity.
+    pub(crate) fn player_notices(&self, e: Entity) -> bool {
+        self.is_player(e) || matches!(self.location(e), Some(loc) if self.player_sees(loc))
+    }
+
+    /// Called when a status first goes into effect.
+    fn status_started(&mut self, e: Entity, status: Status) {
+        use Status::*;
+        if status == Delayed {
+            // Happens all the time, don't bother.
+            return;
+        }
+        self.rebuild_stats(e);
+
+        if !self.player_notices(e) {
+            return;
+        }
+        match status {
+            Confused => {
+                msg!("[One] [is] confused."; self.subject(e));
+            }
+            Slowed => {
+                msg!("[One] slow[s] down."; self.subject(e));
+            }
+            Hasted => {
+                msg!("[One] speed[s] up."; self.subject(e));
+            }
+            Poisoned => {
+                msg!("[One] [is] poisoned."; self.subject(e));
+            }
+            Regenerating => {
+                msg!("[One] start[s] regenerating."; self.subject(e));
+            }
+            Stunned => {
+                msg!("[One] [is] stunned."; self.subject(e));
+            }
+            Burning => {
+                msg!("[One] catch[es] fire."; self.subject(e));
+            }
+            Invisible => {
+                msg!("[One] vanish[es]."; self.subject(e));
+            }
+            Dead | Delayed => {}
+        }
+    }
+
+    /// Called once per turn while a status is in effect.
+    fn status_tick(&mut self, e: Entity, status: Status) {
+        use Status::*;
+        match status {
+            Poisoned => self.damage(e, 1, Damage::Poison, None),
+            Burning => self.damage(e, 2, Damage::Fire, None),
+            Regenerating => {
+                let _ = self.tick_regeneration(e);
             }
+            _ => {}
+        }
+    }
+
+    /// Called when a status goes out of effect.
+    fn status_ended(&mut self, e: Entity, status: Status) {
+        use Status::*;
+        if status == Delayed {
+            return;
+        }
+        self.rebuild_stats(e);
+
+        if !self.player_notices(e) {
+            return;
+        }
+        match status {
+            Confused => {
+                msg!("[One] [is] no longer confused."; self.subject(e));
+            }
+            Slowed => {
+                msg!("[One] speed[s] up."; self.subject(e));
+            }
+            Hasted => {
+                msg!("[One] slow[s] down."; self.subject(e));
+            }
+            Poisoned => {
+                msg!("[One] [is] no longer poisoned."; self.subject(e));
+            }
+            Regenerating => {
+                msg!("[One] stop[s] regenerating."; self.subject(e));
+            }
+            Stunned => {
+                msg!("[One] [is] no longer stunned."; self.subject(e));
+            }
+            Burning => {
+                msg!("[One] stop[s] burning."; self.subject(e));
+            }
+            Invisible => {
+                msg!("[One] reappear[s]."; self.subject(e));
+            }
+            Dead | Delayed => {}
         }
     }
 
@@ -384,21 +775,38 @@ impl World {
             }
         }
 
+        // Add in stat modifiers from statuses.
+        for (status, _) in self.statuses(e) {
+            stats = stats + status.stat_modifier();
+        }
+
+        // Starving creatures fight worse.
+        stats = stats + self.hunger(e).stat_penalty();
+
         // Set the derived stats.
         self.ecs_mut().stats[e].actual = stats;
     }
 
-    /// Consume one unit of nutrition
-    ///
-    /// Return false if the entity has an empty stomach.
-    pub(crate) fn consume_nutrition(&mut self, _: Entity) -> bool {
-        // TODO nutrition system
-        true
-    }
-
     pub(crate) fn really_melee(&mut self, e: Entity, dir: Dir6) -> ActionOutcome {
         let loc = self.location(e)?;
-        let target = self.mob_at(loc.jump(self, dir))?;
+        let target_loc = loc.jump(self, dir);
+        let target = self.mob_at(target_loc)?;
+
+        let block = self.stats(target).block.min(MAX_BLOCK_CHANCE);
+        if block > 0 && self.rng().gen_range(0..100) < block {
+            msg!("[One] block[s] [another's] attack.";
+                self.subject(target), self.object(e));
+            // A blocked attack is still an attack.
+            self.notify_attacked_by(target, e);
+            let anim_tick = self.get_anim_tick();
+            if let Some(anim) = self.ecs_mut().anim.get_mut(target) {
+                anim.anim_start = anim_tick;
+                anim.state = AnimState::MobBlocks;
+            }
+            self.make_noise(target_loc, Noise::Combat, Some(e));
+            self.end_turn(e);
+            return Some(true);
+        }
 
         // XXX: Using power stat for damage, should this be different?
         // Do +5 since dmg 1 is really, really useless.
@@ -413,6 +821,53 @@ impl World {
                 self.subject(e), self.object(target));
         }
         self.damage(target, damage, Damage::Physical, Some(e));
+        if damage > 0 && self.has_intrinsic(e, Intrinsic::Drain) && self.is_alive(target) {
+            self.apply_effect_to_entity(&DRAINING_TOUCH, target, Some(e));
+        }
+        if damage > 0 && self.has_intrinsic(e, Intrinsic::Poisonous) && self.is_alive(target) {
+            self.gain_status(target, Status::Poisoned, POISON_DURATION);
+        }
+        self.make_noise(target_loc, Noise::Combat, Some(e));
+        self.end_turn(e);
+        Some(true)
+    }
+
+    pub(crate) fn really_shoot(&mut self, e: Entity, dir: Dir6) -> ActionOutcome {
+        let range = self.stats(e).ranged_range;
+        if range == 0 {
+            return None;
+        }
+        let origin = self.location(e)?;
+        let end = self.projected_explosion_center(origin, dir, range);
+
+        if let Some(target) = self.mob_at(end) {
+            let advantage = self.attack(e) - self.defense(target) + 2 * self.stats(target).armor;
+            let damage = attack_damage(roll(self.rng()), advantage, 5 + self.stats(e).ranged_power);
+
+            if damage == 0 {
+                msg!("[One] shoot[s] at [another] and miss[es].";
+                    self.subject(e), self.object(target));
+            } else {
+                msg!("[One] shoot[s] [another] for {}.", damage;
+                    self.subject(e), self.object(target));
+            }
+            self.damage(target, damage, Damage::Physical, Some(e));
+            self.make_noise(end, Noise::Combat, Some(e));
+        } else {
+            msg!("[One] shoot[s]."; self.subject(e));
+        }
+
+        // Only animate the projectile if it actually left the shooter's cell.
+        if end != origin {
+            const PROJECTILE_TIME: u32 = 6;
+            let anim_tick = self.get_anim_tick();
+            let projectile = self.spawn_fx(end, AnimState::Projectile);
+            let anim = self.anim_mut(projectile).unwrap();
+            anim.tween_from = origin;
+            anim.tween_start = anim_tick;
+            anim.tween_duration = PROJECTILE_TIME;
+        }
+
         self.end_turn(e);
         Some(true)
     }
@@ -433,45 +888,443 @@ impl World {
         self.gain_xp(e, xp);
     }
 
+    /// Gain or lose experience points.
+    ///
+    /// Negative experience drains levels, but the entity will bottom out at zero experience on
+    /// level zero.
     pub(crate) fn gain_xp(&mut self, e: Entity, xp: i32) {
-        const XP_PER_LEVEL: i32 = 100;
+        let base = match self.base_stats_mut(e) {
+            Some(base) => *base,
+            None => return,
+        };
 
-        let mut new_xp = self.stats(e).xp + xp;
+        let mut new_xp = base.xp + xp;
+        let mut levels = 0;
 
         while new_xp >= XP_PER_LEVEL {
-            self.gain_level(e, 1);
+            levels += 1;
             new_xp -= XP_PER_LEVEL;
         }
 
         // Level drain!
         while new_xp < 0 {
-            self.gain_level(e, -1);
+            levels -= 1;
             new_xp += XP_PER_LEVEL;
         }
+
+        if base.level + levels < 0 {
+            levels = -base.level;
+            new_xp = 0;
+        }
+
         self.base_stats_mut(e).unwrap().xp = new_xp;
+        self.gain_level(e, levels);
         self.rebuild_stats(e);
     }
 
+    /// Drain experience points from an entity.
+    ///
+    /// If `lethal` is set, an entity that doesn't have enough experience left to cover the drain
+    /// dies. Otherwise it just ends up with zero experience on level zero.
+    pub(crate) fn drain_xp(
+        &mut self,
+        e: Entity,
+        amount: u32,
+        lethal: bool,
+        source: Option<Entity>,
+    ) {
+        let base = match self.base_stats_mut(e) {
+            Some(base) => *base,
+            None => return,
+        };
+
+        let amount = i32::try_from(amount).unwrap_or(i32::MAX);
+        let total_xp = base.level * XP_PER_LEVEL + base.xp;
+        if lethal && amount > total_xp {
+            let hp = self.hp(e);
+            self.damage(e, hp + 1, Damage::Drain, source);
+        } else {
+            self.gain_xp(e, -amount);
+        }
+    }
+
     fn gain_level(&mut self, e: Entity, change: i32) {
         if change == 0 {
             return;
         }
-        if change < 0 {
-            // TODO
-            panic!("Level drain not yet implemented");
-        }
 
         self.base_stats_mut(e).unwrap().level += change;
         self.rebuild_stats(e);
 
-        if let Some(health) = self.ecs_mut().health.get_mut(e) {
-            health.wounds = 0;
-        }
+        if change > 0 {
+            if let Some(health) = self.ecs_mut().health.get_mut(e) {
+                health.wounds = 0;
+            }
 
-        if self.is_player(e) {
-            msg!("[One] feel[s] stronger."; self.subject(e));
+            if self.is_player(e) {
+                msg!("[One] feel[s] stronger."; self.subject(e));
+            } else {
+                msg!("[One] look[s] stronger."; self.subject(e));
+            }
         } else {
-            msg!("[One] look[s] stronger."; self.subject(e));
+            // Losing levels lowers maximum health, but shouldn't be what does you in.
+            let max_hp = self.max_hp(e);
+            if let Some(health) = self.ecs_mut().health.get_mut(e) {
+                health.wounds = health.wounds.min((max_hp - 1).max(0));
+            }
+
+            if self.is_player(e) {
+                msg!("[One] feel[s] weaker."; self.subject(e));
+            } else {
+                msg!("[One] look[s] weaker."; self.subject(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Intrinsic, Status, Susceptibility, XP_PER_LEVEL};
+    use crate::{
+        ai::BrainState,
+        animations::AnimState,
+        effect::{Damage, Effect},
+        test_util::{give_item, test_world},
+        Ability, Command, ExternalEntity, Location, Slot, Terrain, World,
+    };
+    use calx::{Dir6, Incremental};
+    use calx_ecs::Entity;
+
+    /// Spawn a mob that can take a few hits.
+    fn spawn_dummy(world: &mut World, loc: Location) -> Entity {
+        let e = world.spawn(&ExternalEntity::from_name("ogre").unwrap(), loc);
+        world.ecs_mut().stats[e].base.base_power = 100;
+        world.rebuild_stats(e);
+        e
+    }
+
+    fn set_attack(world: &mut World, e: Entity, attack: i32) {
+        world.ecs_mut().stats[e].base.base_attack = attack;
+        world.rebuild_stats(e);
+    }
+
+    #[test]
+    fn test_status_ticks() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let max_hp = world.max_hp(player);
+
+        // Damage over time statuses hit once every tick while they last.
+        for &(status, damage) in &[(Status::Poisoned, 1), (Status::Burning, 2)] {
+            world.ecs_mut().health[player].wounds = 0;
+            world.gain_status(player, status, 36);
+            assert!(world.has_status(player, status));
+            for _ in 0..36 {
+                world.tick_statuses(player);
+            }
+            assert_eq!(world.hp(player), max_hp - 2 * damage);
+            assert!(!world.has_status(player, status));
+        }
+
+        world.ecs_mut().health[player].wounds = 5;
+        world.gain_status(player, Status::Regenerating, 36);
+        for _ in 0..36 {
+            world.tick_statuses(player);
+        }
+        assert_eq!(world.hp(player), max_hp - 3);
+        assert!(!world.has_status(player, Status::Regenerating));
+    }
+
+    #[test]
+    fn test_status_sources() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+        let dir = *Dir6::iter()
+            .find(|&&dir| world.can_enter(player, loc.jump(&world, dir)))
+            .unwrap();
+        let next = loc.jump(&world, dir);
+
+        // Fire sets things on fire and lightning stuns.
+        let ogre = world.spawn(&ExternalEntity::from_name("ogre").unwrap(), next);
+        let fire = Effect::Hit {
+            amount: 1,
+            damage: Damage::Fire,
+        };
+        world.apply_effect_to_entity(&fire, ogre, None);
+        assert!(world.has_status(ogre, Status::Burning));
+        let lightning = Effect::Hit {
+            amount: 1,
+            damage: Damage::Electricity,
+        };
+        world.apply_effect_to_entity(&lightning, ogre, None);
+        assert!(world.has_status(ogre, Status::Stunned));
+        assert!(!world.ticks_this_frame(ogre));
+
+        // Fireproof things don't burn.
+        let efreet = world.spawn(&ExternalEntity::from_name("efreeti").unwrap(), next);
+        world.apply_effect_to_entity(&fire, efreet, None);
+        assert!(!world.has_status(efreet, Status::Burning));
+        world.kill_entity(efreet);
+        world.kill_entity(ogre);
+
+        // Snake bites are poisonous.
+        let snake = world.spawn(&ExternalEntity::from_name("snake").unwrap(), next);
+        for _ in 0..100 {
+            if world.hp(player) < world.max_hp(player) {
+                break;
+            }
+            world.really_melee(snake, dir + 3);
+        }
+        assert!(world.hp(player) < world.max_hp(player));
+        assert!(world.has_status(player, Status::Poisoned));
+        world.kill_entity(snake);
+
+        let scroll = give_item(&mut world, player, "scroll of regeneration");
+        world.update(&Command::UntargetedAbility {
+            ability: Ability::Regenerate,
+            item: Some(scroll),
+        });
+        assert!(world.has_status(player, Status::Regenerating));
+
+        let scroll = give_item(&mut world, player, "scroll of invisibility");
+        world.update(&Command::UntargetedAbility {
+            ability: Ability::Invisibility,
+            item: Some(scroll),
+        });
+        assert!(world.has_status(player, Status::Invisible));
+        let rat = world.spawn(&ExternalEntity::from_name("rat").unwrap(), next);
+        assert!(world.can_sense(rat, player));
+        world.place_entity(rat, next.jump(&world, dir));
+        assert!(!world.can_sense(rat, player));
+    }
+
+    #[test]
+    fn test_susceptibility() {
+        use Intrinsic::*;
+
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap().jump(&world, Dir6::North);
+        world.set_terrain(loc, Terrain::Ground);
+        let ogre = spawn_dummy(&mut world, loc);
+        let base_intrinsics = world.ecs().stats[ogre].base.intrinsics;
+
+        for &(damage, resist, immune, vulnerable) in &[
+            (Damage::Fire, ResistFire, ImmuneFire, VulnerableFire),
+            (Damage::Poison, ResistPoison, ImmunePoison, VulnerablePoison),
+        ] {
+            for &(intrinsics, susceptibility, amount) in &[
+                (&[][..], Susceptibility::Normal, 10),
+                (&[resist][..], Susceptibility::Resistant, 5),
+                (&[immune][..], Susceptibility::Immune, 0),
+                (&[vulnerable][..], Susceptibility::Vulnerable, 20),
+                // Resistance and vulnerability cancel out, immunity beats both.
+                (&[resist, vulnerable][..], Susceptibility::Normal, 10),
+                (&[immune, vulnerable][..], Susceptibility::Immune, 0),
+            ] {
+                world.ecs_mut().stats[ogre].base.intrinsics = base_intrinsics;
+                for &i in intrinsics {
+                    world.ecs_mut().stats[ogre].base.add_intrinsic(i);
+                }
+                world.rebuild_stats(ogre);
+                world.ecs_mut().health[ogre].wounds = 0;
+
+                assert_eq!(world.susceptibility(ogre, damage), susceptibility);
+                world.damage(ogre, 10, damage, None);
+                assert_eq!(
+                    world.max_hp(ogre) - world.hp(ogre),
+                    amount,
+                    "{:?} against {:?}",
+                    damage,
+                    susceptibility
+                );
+            }
+        }
+
+        // Armor soaks physical damage only.
+        world.ecs_mut().stats[ogre].base.intrinsics = base_intrinsics;
+        world.ecs_mut().stats[ogre].base.armor = 10;
+        world.rebuild_stats(ogre);
+        for &(damage, amount) in &[
+            (Damage::Physical, 5),
+            (Damage::Fire, 10),
+            (Damage::Poison, 10),
+            (Damage::Electricity, 10),
+        ] {
+            world.ecs_mut().health[ogre].wounds = 0;
+            world.damage(ogre, 10, damage, None);
+            assert_eq!(
+                world.max_hp(ogre) - world.hp(ogre),
+                amount,
+                "{:?}",
+                damage
+            );
+        }
+    }
+
+    #[test]
+    fn test_deathsplosion_chain() {
+        fn run() -> (World, Vec<Entity>) {
+            let mut world = test_world();
+            let player = world.player().unwrap();
+            let mut loc = world.location(player).unwrap();
+            let mut line = Vec::new();
+            for _ in 0..6 {
+                loc = loc.jump(&world, Dir6::North);
+                world.set_terrain(loc, Terrain::Ground);
+                line.push(loc);
+            }
+
+            // Stand far enough from the player to not hurt them. Every blast is strong enough to
+            // kill the next one in line.
+            let horrors: Vec<Entity> = line[3..]
+                .iter()
+                .zip(&[30, 20, 10])
+                .map(|(&loc, &power)| {
+                    let e = world.spawn(&ExternalEntity::from_name("eye horror").unwrap(), loc);
+                    world.ecs_mut().stats[e].base.base_power = power;
+                    world.rebuild_stats(e);
+                    e
+                })
+                .collect();
+
+            world.damage(horrors[0], 1000, Damage::Physical, Some(player));
+            (world, horrors)
+        }
+
+        let (world, horrors) = run();
+        for &e in &horrors {
+            assert!(!world.is_alive(e));
+        }
+        let player = world.player().unwrap();
+        assert_eq!(world.hp(player), world.max_hp(player));
+
+        // Same start, same outcome, including all the explosion effects and the random number
+        // generator state.
+        let snapshot = |world: &World| {
+            let entities: Vec<_> = world
+                .ecs()
+                .iter()
+                .map(|&e| (e, world.location(e), world.hp(e), world.anim(e).cloned()))
+                .collect();
+            (format!("{:?}", entities), ron::ser::to_string(&world.rng).unwrap())
+        };
+        let (world2, _) = run();
+        assert_eq!(snapshot(&world), snapshot(&world2));
+    }
+
+    #[test]
+    fn test_blocking() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        set_attack(&mut world, player, 1000);
+        let dir = Dir6::North;
+        let loc = world.location(player).unwrap().jump(&world, dir);
+        world.set_terrain(loc, Terrain::Ground);
+        let ogre = spawn_dummy(&mut world, loc);
+
+        let attack = |world: &mut World| {
+            world.ecs_mut().health[ogre].wounds = 0;
+            world.set_brain_state(ogre, BrainState::Asleep);
+            world.ecs_mut().anim[ogre].state = AnimState::Mob;
+            world.really_melee(player, dir);
+            world.hp(ogre) == world.max_hp(ogre)
+        };
+
+        // Without a block chance every attack lands.
+        for _ in 0..20 {
+            assert!(!attack(&mut world));
         }
+
+        // Block chance is capped, so some attacks still get through.
+        world.ecs_mut().stats[ogre].base.block = 1000;
+        world.rebuild_stats(ogre);
+        let (mut blocks, mut hits) = (0, 0);
+        for _ in 0..40 {
+            if attack(&mut world) {
+                blocks += 1;
+                // Blocking still makes the attacker an enemy and shows up on screen.
+                assert_eq!(world.brain_state(ogre), Some(BrainState::Hunting(player)));
+                assert_eq!(world.anim(ogre).unwrap().state, AnimState::MobBlocks);
+            } else {
+                hits += 1;
+            }
+        }
+        assert!(blocks > 0 && hits > 0, "{} blocks, {} hits", blocks, hits);
+    }
+
+    #[test]
+    fn test_level_drain() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap().jump(&world, Dir6::North);
+        world.set_terrain(loc, Terrain::Ground);
+        let ogre = spawn_dummy(&mut world, loc);
+        world.ecs_mut().stats[ogre].base.level = 3;
+        world.rebuild_stats(ogre);
+        let max_hp = world.max_hp(ogre);
+        world.ecs_mut().health[ogre].wounds = max_hp - 1;
+
+        // Drain that the victim can cover costs levels but doesn't kill, even if the lowered
+        // maximum health is less than the wounds taken.
+        world.drain_xp(ogre, 2 * XP_PER_LEVEL as u32, true, Some(player));
+        assert!(world.is_alive(ogre));
+        assert_eq!(world.ecs().stats[ogre].base.level, 1);
+        assert!(world.max_hp(ogre) < max_hp);
+        assert!(world.hp(ogre) > 0);
+
+        // Non-lethal drain bottoms out at level zero.
+        world.drain_xp(ogre, u32::MAX, false, Some(player));
+        assert!(world.is_alive(ogre));
+        assert_eq!(world.ecs().stats[ogre].base.level, 0);
+        assert_eq!(world.ecs().stats[ogre].base.xp, 0);
+
+        // Lethal drain kills when there's no experience left to drain.
+        world.drain_xp(ogre, u32::MAX, true, Some(player));
+        assert!(!world.is_alive(ogre));
+    }
+
+    #[test]
+    fn test_shooting() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let dir = Dir6::North;
+
+        // Clear a line of fire.
+        let mut line = Vec::new();
+        let mut loc = world.location(player).unwrap();
+        for _ in 0..7 {
+            loc = loc.jump(&world, dir);
+            world.set_terrain(loc, Terrain::Ground);
+            line.push(loc);
+        }
+
+        assert!(!world.can_command(&Command::Shoot(dir)));
+        let bow = give_item(&mut world, player, "bow");
+        world.equip_item(bow, player, Slot::Ranged);
+        assert!(world.can_command(&Command::Shoot(dir)));
+        let range = world.stats(player).ranged_range as usize;
+        set_attack(&mut world, player, 1000);
+
+        // Targets past the range are safe.
+        let far = spawn_dummy(&mut world, line[range]);
+        assert_eq!(world.find_ranged_target(player, dir, range), None);
+        world.really_shoot(player, dir);
+        assert_eq!(world.hp(far), world.max_hp(far));
+
+        // The first mob in line takes the hit.
+        let front = spawn_dummy(&mut world, line[1]);
+        let back = spawn_dummy(&mut world, line[2]);
+        assert_eq!(world.find_ranged_target(player, dir, range), Some(front));
+        world.really_shoot(player, dir);
+        assert!(world.hp(front) < world.max_hp(front));
+        assert_eq!(world.hp(back), world.max_hp(back));
+
+        // A hopeless shooter misses.
+        let hp = world.hp(front);
+        set_attack(&mut world, player, -1000);
+        world.really_shoot(player, dir);
+        assert_eq!(world.hp(front), hp);
     }
 }
@@ -1,4 +1,5 @@
 use crate::{Location, World};
+use calx_ecs::Entity;
 use serde::{Deserialize, Serialize};
 use std::slice;
 use vitral::SRgba;
@@ -12,7 +13,10 @@ impl World {
 
     /// Return terrain at location.
     pub fn terrain(&self, loc: Location) -> Terrain {
-        let mut t = self.world_cache.get_terrain(loc);
+        let mut t = match self.terrain_overrides.get(&loc) {
+            Some(&t) => t,
+            None => self.world_cache.get_terrain(loc),
+        };
 
         if t == Terrain::Door && self.has_mobs(loc) {
             // Standing in the doorway opens the door.
@@ -22,6 +26,32 @@ impl World {
         t
     }
 
+    /// Change the terrain at location.
+    ///
+    /// Viewers who can see the location get their field of view recomputed and cached paths are
+    /// dropped.
+    pub(crate) fn set_terrain(&mut self, loc: Location, terrain: Terrain) {
+        if terrain == self.world_cache.get_terrain(loc) {
+            // Back to the original terrain, no need to save it.
+            self.terrain_overrides.remove(&loc);
+        } else {
+            self.terrain_overrides.insert(loc, terrain);
+        }
+
+        self.invalidate_paths();
+
+        let viewers: Vec<Entity> = self
+            .ecs()
+            .map_memory
+            .ent_iter()
+            .filter(|&&e| self.ecs().map_memory[e].seen.contains(loc))
+            .cloned()
+            .collect();
+        for e in viewers {
+            self.do_fov(e);
+        }
+    }
+
     /// If location contains a portal, return the destination of the portal.
     pub fn portal(&self, loc: Location) -> Option<Location> { self.world_cache.get_portal(loc) }
 
@@ -283,4 +313,22 @@ mod test {
             Some(Terrain::Grass)
         );
     }
+
+    #[test]
+    fn test_set_terrain() {
+        use crate::test_util::test_world;
+
+        let mut world = test_world();
+        let loc = world.location(world.player().unwrap()).unwrap();
+        let original = world.terrain(loc);
+
+        world.set_terrain(loc, Terrain::Magma);
+        assert_eq!(world.terrain(loc), Terrain::Magma);
+        assert_eq!(world.terrain_overrides.len(), 1);
+
+        // Changing terrain back to what was generated clears the override.
+        world.set_terrain(loc, original);
+        assert_eq!(world.terrain(loc), original);
+        assert!(world.terrain_overrides.is_empty());
+    }
 }
@@ -0,0 +1,34 @@
+//! Shared fixtures for unit tests
+
+use crate::{Command, ExternalEntity, World, WorldSeed, WorldSkeleton};
+use calx::Incremental;
+use calx_ecs::Entity;
+
+/// Create a small dungeon world with a standard player character.
+pub fn test_world() -> World {
+    World::new(&WorldSeed {
+        rng_seed: 1,
+        world_skeleton: WorldSkeleton::dungeon_dive(),
+        player_character: ExternalEntity::from_name("player").unwrap(),
+        active_radius: crate::DEFAULT_ACTIVE_RADIUS,
+    })
+}
+
+/// Run the world until the player gets to act or dies.
+pub fn wait_for_turn(world: &mut World) {
+    while world.player().is_some() && !world.player_can_act() {
+        world.update(&Command::Wait);
+    }
+}
+
+/// Spawn a named item in the owner's bag and run the world until the player can act.
+pub fn give_item(world: &mut World, owner: Entity, name: &str) -> Entity {
+    let item = world.spawn(
+        &ExternalEntity::from_name(name).unwrap(),
+        world.location(owner).unwrap(),
+    );
+    let slot = world.free_bag_slot(owner).unwrap();
+    world.equip_item(item, owner, slot);
+    wait_for_turn(world);
+    item
+}
@@ -0,0 +1,287 @@
+//! Hidden traps and other things that go off when stepped on
+
+use crate::{
+    effect::{Damage, Effect},
+    msg,
+    sector::Sector,
+    sound::Noise,
+    ActionOutcome, AnimState, ItemType, Location, World,
+};
+use calx::{HexGeom, RngExt};
+use calx_ecs::Entity;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+/// What happens when a trap goes off.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TrapKind {
+    /// Moves the victim to a random spot in the sector.
+    Teleport,
+    /// Releases a cloud of gas that confuses everyone nearby.
+    ConfusionGas,
+    /// Drops the victim to the floor below.
+    Pit,
+    /// Makes a racket that brings monsters running.
+    Alarm,
+}
+
+impl TrapKind {
+    pub const ALL: [TrapKind; 4] = [
+        TrapKind::Teleport,
+        TrapKind::ConfusionGas,
+        TrapKind::Pit,
+        TrapKind::Alarm,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TrapKind::Teleport => "teleport trap",
+            TrapKind::ConfusionGas => "gas trap",
+            TrapKind::Pit => "trapdoor",
+            TrapKind::Alarm => "alarm trap",
+        }
+    }
+}
+
+/// Trap component.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Trap {
+    pub kind: TrapKind,
+    /// Hidden traps aren't shown until someone finds them or sets them off.
+    pub hidden: bool,
+}
+
+impl World {
+    pub fn is_trap(&self, e: Entity) -> bool { self.ecs().trap.contains(e) }
+
+    /// Return whether the entity is a trap the player hasn't found yet.
+    pub fn is_hidden(&self, e: Entity) -> bool { self.ecs().trap.get(e).is_some_and(|t| t.hidden) }
+
+    /// Return the chance for the entity to notice a hidden trap nearby.
+    pub fn perception(&self, e: Entity) -> f32 {
+        const BASE_PERCEPTION: f32 = 0.1;
+        const PERCEPTION_PER_LEVEL: f32 = 0.05;
+        const MAX_PERCEPTION: f32 = 0.9;
+
+        let level = self.stats(e).level.max(0) as f32;
+        (BASE_PERCEPTION + PERCEPTION_PER_LEVEL * level).min(MAX_PERCEPTION)
+    }
+
+    /// Spend a turn searching for hidden traps.
+    pub(crate) fn entity_search(&mut self, e: Entity) -> ActionOutcome {
+        // Searching deliberately finds traps faster than just walking past them.
+        const SEARCH_EFFORT: f32 = 3.0;
+
+        self.search(e, SEARCH_EFFORT);
+        self.idle(e)
+    }
+
+    /// Look for hidden traps around the entity.
+    ///
+    /// Effort multiplies the chance of noticing each trap.
+    pub(crate) fn search(&mut self, e: Entity, effort: f32) {
+        const SEARCH_RADIUS: u32 = 2;
+
+        // Only the player has any use for finding traps.
+        if !self.is_player(e) {
+            return;
+        }
+        let origin = match self.location(e) {
+            Some(loc) => loc,
+            None => return,
+        };
+
+        let traps: Vec<Entity> = self
+            .sphere_volume(origin, SEARCH_RADIUS)
+            .0
+            .into_iter()
+            .flat_map(|loc| self.entities_at(loc))
+            .filter(|&x| self.is_hidden(x))
+            .collect();
+
+        let chance = self.perception(e) * effort;
+        for trap in traps {
+            if self.rng().with_chance(chance) {
+                self.ecs_mut().trap[trap].hidden = false;
+                msg!("[One] find[s] [a thing]."; self.subject(e), self.object(trap));
+            }
+        }
+    }
+
+    /// Set off everything that triggers when a creature steps on location.
+    pub(crate) fn step_triggers(&mut self, e: Entity, loc: Location) {
+        for x in self.entities_at(loc) {
+            if self.location(e) != Some(loc) {
+                // Got moved away by the previous trigger.
+                break;
+            }
+
+            if self.is_trap(x) {
+                self.trigger_trap(e, x, loc);
+            } else if let Some(ItemType::Instant(ability)) = self.item_type(x) {
+                if self.ability_effect(e, ability, None, Some(x)).is_some() {
+                    self.identify_by_use(e, x);
+                    self.drain_charge(e, x);
+                }
+            }
+        }
+    }
+
+    fn trigger_trap(&mut self, e: Entity, trap: Entity, loc: Location) {
+        const PIT_DAMAGE: i32 = 3;
+
+        let seen = self.player_sees(loc);
+        if seen {
+            self.ecs_mut().trap[trap].hidden = false;
+            msg!("[One] set[s] off [a thing]!"; self.subject(e), self.object(trap));
+        }
+
+        match self.ecs().trap[trap].kind {
+            TrapKind::Teleport => {
+                let spots = self.open_locations(e, Sector::from(loc));
+                if let Some(&dest) = spots.choose(self.rng()) {
+                    self.jump_to(e, dest);
+                }
+            }
+            TrapKind::ConfusionGas => {
+                let volume = self.sphere_volume(loc, 1);
+                for &pt in &volume.0 {
+                    self.spawn_fx(pt, AnimState::Smoke);
+                }
+                self.apply_effect(&Effect::Confuse, &volume, None);
+            }
+            TrapKind::Pit => {
+                let below = Location {
+                    z: loc.z - 1,
+                    ..loc
+                };
+                // Land as close to the spot below as possible.
+                let dest = self
+                    .open_locations(e, Sector::from(below))
+                    .into_iter()
+                    .min_by_key(|&l| below.v2_at(l).map_or(i32::MAX, |v| v.hex_dist()));
+                if let Some(dest) = dest {
+                    if seen {
+                        msg!("[One] fall[s] through!"; self.subject(e));
+                    }
+                    self.jump_to(e, dest);
+                    // Allies jump down after their leader like they'd follow them down stairs.
+                    self.bring_followers(e, loc, dest);
+                }
+                self.damage(e, PIT_DAMAGE, Damage::Physical, None);
+            }
+            TrapKind::Alarm => {
+                if seen {
+                    msg!("An alarm blares.");
+                }
+                self.make_noise(loc, Noise::Alarm, Some(e));
+            }
+        }
+    }
+
+    /// Return the locations in sector the entity could be put in.
+    fn open_locations(&self, e: Entity, sector: Sector) -> Vec<Location> {
+        if self.world_cache.sector_spec(sector).is_none() {
+            return Vec::new();
+        }
+        sector
+            .iter()
+            .filter(|&l| self.can_enter(e, l) && !self.terrain(l).is_door())
+            .filter(|&l| !self.entities_at(l).into_iter().any(|x| self.is_trap(x)))
+            .collect()
+    }
+
+    /// Move the entity to a far-off location instantly.
+    fn jump_to(&mut self, e: Entity, loc: Location) {
+        self.place_entity(e, loc);
+        // Don't animate a slide over to the new spot.
+        if let Some(anim) = self.anim_mut(e) {
+            anim.tween_duration = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        effect::Damage,
+        test_util::{test_world, wait_for_turn},
+        Command, ExternalEntity, Location, World,
+    };
+    use calx::{Dir6, Incremental};
+    use calx_ecs::Entity;
+
+    /// Spawn something next to the player and return it and the direction to step on it.
+    fn spawn_next_to_player(world: &mut World, name: &str) -> (Entity, Dir6) {
+        let player = world.player().unwrap();
+        let loc = world.location(player).unwrap();
+        let dir = *Dir6::iter()
+            .find(|&&dir| {
+                let loc = loc.jump(world, dir);
+                world.can_enter(player, loc) && world.entities_at(loc).is_empty()
+            })
+            .unwrap();
+        let e = world.spawn(
+            &ExternalEntity::from_name(name).unwrap(),
+            loc.jump(world, dir),
+        );
+        wait_for_turn(world);
+        (e, dir)
+    }
+
+    #[test]
+    fn test_traps() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+        let start = world.location(player).unwrap();
+
+        // Hidden traps are found by searching.
+        let (trap, _) = spawn_next_to_player(&mut world, "alarm trap");
+        assert!(world.is_hidden(trap));
+        for _ in 0..100 {
+            if !world.is_hidden(trap) {
+                break;
+            }
+            world.update(&Command::Search);
+            wait_for_turn(&mut world);
+        }
+        assert!(!world.is_hidden(trap));
+
+        // Pit drops you and your allies on the floor below.
+        let (trap, dir) = spawn_next_to_player(&mut world, "trapdoor");
+        let trap_loc = world.location(trap).unwrap();
+        let ally_loc = Dir6::iter()
+            .map(|&dir| trap_loc.jump(&world, dir))
+            .find(|&loc| world.can_enter(player, loc))
+            .unwrap();
+        let dog = world.spawn(&ExternalEntity::from_name("dog").unwrap(), ally_loc);
+        world.make_ally(dog, player);
+        world.update(&Command::Step(dir));
+        let loc = world.location(player).unwrap();
+        assert_eq!(loc.z, start.z - 1);
+        assert_eq!(world.location(dog).unwrap().z, start.z - 1);
+
+        // Teleport moves you somewhere else in the sector.
+        let (trap, dir) = spawn_next_to_player(&mut world, "teleport trap");
+        let trap_loc = world.location(trap).unwrap();
+        world.update(&Command::Step(dir));
+        let loc: Location = world.location(player).unwrap();
+        assert_ne!(loc, trap_loc);
+        assert_eq!(loc.z, trap_loc.z);
+        assert!(!world.is_hidden(trap));
+    }
+
+    #[test]
+    fn test_instant_items() {
+        let mut world = test_world();
+        let player = world.player().unwrap();
+
+        let (orb, dir) = spawn_next_to_player(&mut world, "healing orb");
+        world.damage(player, 3, Damage::Physical, None);
+        assert!(world.hp(player) < world.max_hp(player));
+
+        world.update(&Command::Step(dir));
+        assert_eq!(world.hp(player), world.max_hp(player));
+        assert!(world.location(orb).is_none());
+    }
+}
@@ -28,6 +28,36 @@ vaults! {VAULTS,
       #....#
       ##++##
     ",
+    "
+      ######
+      #....#
+    ###.$$.###
+    #..^..^..#
+    +...aa...+
+    #..^..^..#
+    ###.$$.###
+      #....#
+      ######
+    ",
+}
+
+vaults! {SHOPS,
+    "
+    ####+####
+    #.......#
+    #...k...#
+    #.......#
+    #########
+    ",
+    "
+      ##+##
+     #.....#
+    #...k...#
+    +.......+
+    #.......#
+     #.....#
+      #####
+    ",
 }
 
 vaults! {ENTRANCES,
@@ -1,23 +1,29 @@
 use crate::{
-    ai, animations, components, desc, flags::Flags, item, spatial::Spatial, spec::EntitySpawn,
-    stats, world_cache::WorldCache, Distribution, ExternalEntity, Location, Rng, WorldSkeleton,
+    ai, animations, components, desc, effect, flags::{self, Flags}, hunger, identify, item,
+    pathing::PathCache, shop, spatial::Spatial, spec::EntitySpawn, stats, trap, world_cache::WorldCache,
+    Distribution, ExternalEntity, Location, Rng, Sector, Slot, Terrain, WorldSkeleton,
 };
 use calx::seeded_rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 pub const GAME_VERSION: &str = "0.1.0";
 
 calx_ecs::build_ecs! {
+    ability: effect::Abilities,
     anim: animations::Anim,
     brain: ai::Brain,
+    container: item::Container,
     desc: desc::Desc,
     health: stats::Health,
     item: item::Item,
     map_memory: components::MapMemory,
+    satiation: hunger::Satiation,
     stacking: item::Stacking,
     stats: stats::StatsComponent,
     status: stats::Statuses,
+    trap: trap::Trap,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -25,6 +31,9 @@ pub struct WorldSeed {
     pub rng_seed: u32,
     pub world_skeleton: WorldSkeleton,
     pub player_character: ExternalEntity,
+    /// Mobs further than this many sectors away from the player are frozen in place.
+    #[serde(default = "flags::default_active_radius")]
+    pub active_radius: i32,
 }
 
 /// Toplevel game state object.
@@ -41,10 +50,21 @@ pub struct World {
     generated_spawns: HashSet<(Location, EntitySpawn)>,
     /// Spatial index for game entities.
     pub(crate) spatial: Spatial,
+    /// Terrain changed during play, overrides the generated terrain in `world_cache`.
+    ///
+    /// Only cells that differ from the generated terrain are stored.
+    pub(crate) terrain_overrides: BTreeMap<Location, Terrain>,
+    /// What unidentified magic items look like, keyed by item kind.
+    pub(crate) item_appearances: BTreeMap<String, desc::Desc>,
+    /// Item kinds the player has identified.
+    pub(crate) identified_kinds: BTreeSet<String>,
     /// Global gamestate flags.
     pub(crate) flags: Flags,
     /// Persistent random number generator.
     pub(crate) rng: Rng,
+    /// Pathfinding maps for mobs, regenerated on demand.
+    #[serde(skip)]
+    pub(crate) path_cache: RefCell<PathCache>,
 }
 
 impl World {
@@ -55,10 +75,16 @@ impl World {
             world_cache: WorldCache::new(world_seed.rng_seed, world_seed.world_skeleton.clone()),
             generated_spawns: Default::default(),
             spatial: Default::default(),
+            terrain_overrides: Default::default(),
+            item_appearances: identify::shuffle_appearances(world_seed.rng_seed),
+            identified_kinds: Default::default(),
             flags: Default::default(),
             rng: seeded_rng(&world_seed.rng_seed),
+            path_cache: Default::default(),
         };
 
+        ret.flags.active_radius = world_seed.active_radius;
+
         ret.spawn_player(
             ret.world_cache.player_entrance(),
             &world_seed.player_character,
@@ -77,8 +103,33 @@ impl World {
             // Create one-off RNG from just the spawn info, will always run the same for same info.
             let mut rng = calx::seeded_rng(&(seed, loc, s));
             // Construct loadout from the spawn info and generate it in world.
-            self.spawn(&s.sample(&mut rng), *loc);
+            let mut entity = s.sample(&mut rng);
+            self.fill_container(&mut entity, *loc, &mut rng);
+            self.spawn(&entity, *loc);
             self.generated_spawns.insert((*loc, s.clone()));
         }
     }
+
+    /// Put random loot suited to the location in a generated container.
+    pub(crate) fn fill_container(&self, entity: &mut ExternalEntity, loc: Location, rng: &mut Rng) {
+        let (count, capacity, gold) = match entity.loadout.container {
+            Some(c) => (c.loot.min(c.capacity), c.capacity, c.gold),
+            None => return,
+        };
+        if count < capacity {
+            if let Some(gold) = shop::gold_pieces(gold) {
+                entity.contents.insert(Slot::Bag(count), gold);
+            }
+        }
+        let spec = match self.world_cache.sector_spec(Sector::from(loc)) {
+            Some(spec) => spec,
+            None => return,
+        };
+
+        for i in 0..count {
+            if let Some(loot) = spec.sample_loot(rng) {
+                entity.contents.insert(Slot::Bag(i), loot.sample(rng));
+            }
+        }
+    }
 }
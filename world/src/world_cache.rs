@@ -1,7 +1,7 @@
 use crate::{
     location::{Location, Portal},
     map::MapCell,
-    sector::{self, Sector, WorldSkeleton},
+    sector::{self, ConnectedSectorSpec, Sector, WorldSkeleton},
     spec::EntitySpawn,
     terrain::Terrain,
 };
@@ -65,8 +65,29 @@ impl WorldCache {
             .map(|&p| loc + p)
     }
 
+    /// Return the origins of the portals that lead into location.
+    pub fn get_portal_origins(&self, loc: Location) -> Vec<Location> {
+        // Finalizing a sector makes both ends of its stairwells, so this sees every portal in.
+        self.finalize(Sector::from(loc));
+        self.internal_cache
+            .borrow()
+            .portal_origins
+            .get(&loc)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     pub fn sector_exists(&self, sector: Sector) -> bool { self.skeleton.contains_key(&sector) }
 
+    /// Return the generation spec for a sector in the world.
+    pub fn sector_spec(&self, sector: Sector) -> Option<ConnectedSectorSpec<'_>> {
+        if self.sector_exists(sector) {
+            Some(ConnectedSectorSpec::new(self.seed, sector, &self.skeleton))
+        } else {
+            None
+        }
+    }
+
     /// Return latest list of spawns.
     ///
     /// `WorldCache` will return spawns from regions that have been loaded into cache. Caller will
@@ -232,11 +253,16 @@ impl WorldCache {
     }
 
     /// Punch a (one-way) portal between two points.
-    fn portal(&self, origin: Location, destination: Location) {
-        self.internal_cache
-            .borrow_mut()
+    pub(crate) fn portal(&self, origin: Location, destination: Location) {
+        let mut cache = self.internal_cache.borrow_mut();
+        cache
             .portals
             .insert(origin, Portal::new(origin, destination));
+        // Stairwells get punched again when the sector at the other end is finalized.
+        let origins = cache.portal_origins.entry(destination).or_default();
+        if !origins.contains(&origin) {
+            origins.push(origin);
+        }
     }
 }
 
@@ -266,6 +292,8 @@ struct InternalCache {
 
     terrain: HashMap<Location, Terrain>,
     portals: HashMap<Location, Portal>,
+    /// Reverse lookup for portals, destinations to origins.
+    portal_origins: HashMap<Location, Vec<Location>>,
 
     pending_spawns: HashMap<Sector, Vec<(Location, EntitySpawn)>>,
     spawn_queue: Vec<(Location, EntitySpawn)>,